@@ -1,5 +1,6 @@
 #![feature(stdin_forwarders)]
 
+use std::env;
 use std::io;
 
 use advent_of_code_2021_rs::day10::*;
@@ -13,5 +14,16 @@ fn main() -> io::Result<()> {
     println!("{}", part1_result);
     let part2_result = part2(&input);
     println!("{}", part2_result);
+
+    // --diagnostics lists every corrupted or incomplete line
+    match env::args().nth(1).as_deref() {
+        None => {},
+        Some("--diagnostics") => {
+            for diagnostic in diagnostics(&BracketSyntax::navigation(), &input) {
+                println!("<stdin>:{}", diagnostic);
+            }
+        },
+        Some(_) => eprintln!("usage: day10 [--diagnostics]"),
+    }
     Ok(())
 }
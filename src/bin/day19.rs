@@ -1,3 +1,5 @@
+use std::env;
+use std::fs;
 use std::io;
 use std::io::{Read, stdin};
 
@@ -10,5 +12,19 @@ fn main() -> io::Result<()> {
     println!("{}", part1_result);
     let part2_result = part2(&buf);
     println!("{}", part2_result);
+
+    // optionally export the assembled map: --ply <file> and/or --obj <file>
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        return Ok(());
+    }
+    let map = solve(&buf, MIN_OVERLAP).unwrap();
+    for option in args.chunks(2) {
+        match option {
+            [flag, path] if flag == "--ply" => fs::write(path, map.to_ply())?,
+            [flag, path] if flag == "--obj" => fs::write(path, map.to_obj())?,
+            _ => eprintln!("usage: day19 [--ply <file>] [--obj <file>]"),
+        }
+    }
     Ok(())
 }
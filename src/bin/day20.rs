@@ -1,5 +1,8 @@
+use std::env;
+use std::fs;
 use std::io;
 use std::io::{Read, stdin};
+use std::path::Path;
 
 use advent_of_code_2021_rs::day20::*;
 
@@ -10,5 +13,29 @@ fn main() -> io::Result<()> {
     println!("{}", part1_result);
     let part2_result = part2(&buf);
     println!("{}", part2_result);
+
+    // optionally render the first steps: --flipbook <steps> or --frames <dir> <steps>
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {},
+        ["--flipbook", steps] => {
+            let frames: Vec<Image> = enhancements(&buf).unwrap().take(parse_steps(steps) + 1).collect();
+            print!("{}", flipbook(&frames));
+        },
+        ["--frames", dir, steps] => {
+            let frames: Vec<Image> = enhancements(&buf).unwrap().take(parse_steps(steps) + 1).collect();
+            let width = frames.last().unwrap().width();
+            fs::create_dir_all(dir)?;
+            for (step, frame) in frames.iter().enumerate() {
+                let margin = (width - frame.width()) / 2 + 1;
+                fs::write(Path::new(dir).join(format!("step_{:03}.pgm", step)), frame.to_pgm(margin))?;
+            }
+        },
+        _ => eprintln!("usage: day20 [--flipbook <steps> | --frames <dir> <steps>]"),
+    }
     Ok(())
 }
+
+fn parse_steps(steps: &str) -> usize {
+    steps.parse().expect("steps must be a number")
+}
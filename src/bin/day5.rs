@@ -1,5 +1,7 @@
 #![feature(stdin_forwarders)]
 
+use std::env;
+use std::fs;
 use std::io;
 
 use advent_of_code_2021_rs::day5::*;
@@ -11,10 +13,38 @@ fn main() -> io::Result<()> {
         .filter_map(|x| x.ok())
         .collect();
 
-    let part1_result = part1(&input);
-    println!("{}", part1_result);
+    // optionally inspect the map with diagonals: --heatmap, --pgm <file> or --at-least <n>,
+    // after --any-slope to also take lines that aren't at 45 degrees
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let slopes = if args.first().is_some_and(|arg| arg == "--any-slope") {
+        args.remove(0);
+        Slopes::Any
+    } else {
+        Slopes::Strict
+    };
 
-    let part2_result = part2(&input);
-    println!("{}", part2_result);
+    if slopes == Slopes::Strict {
+        let part1_result = part1(&input);
+        println!("{}", part1_result);
+
+        let part2_result = part2(&input);
+        println!("{}", part2_result);
+    } else {
+        let lines = parse(&input, slopes).unwrap();
+        println!("{}", count_overlaps(&lines, false, Method::Raster));
+        println!("{}", count_overlaps(&lines, true, Method::Raster));
+    }
+
+    let map = || overlap_map(&parse(&input, slopes).unwrap(), true);
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => {},
+        ["--heatmap"] => print!("{}", map().to_ascii()),
+        ["--pgm", path] => fs::write(path, map().to_pgm())?,
+        ["--at-least", threshold] => {
+            let threshold: u32 = threshold.parse().expect("threshold must be a number");
+            println!("{}", map().at_least(threshold));
+        },
+        _ => eprintln!("usage: day5 [--any-slope] [--heatmap | --pgm <file> | --at-least <n>]"),
+    }
     Ok(())
 }
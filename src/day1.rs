@@ -1,18 +1,59 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sweep {
+  pub increasing: usize,
+  pub decreasing: usize,
+  pub plateau: usize,
+}
+
+// Compares the sums of consecutive windows of `window` depths. Neighbouring
+// sums share all but one depth each, so it's enough to compare a[i] with
+// a[i + window], and only the last `window` depths are kept in memory.
+pub fn sweep(depths: impl IntoIterator<Item = u32>, window: usize) -> Sweep {
+  assert!(window > 0, "window must hold at least one depth");
+  let mut sweep = Sweep::default();
+  let mut recent: VecDeque<u32> = VecDeque::with_capacity(window + 1);
+  for depth in depths {
+    recent.push_back(depth);
+    if recent.len() > window {
+      let old = recent.pop_front().unwrap();
+      match depth.cmp(&old) {
+        Ordering::Greater => sweep.increasing += 1,
+        Ordering::Less => sweep.decreasing += 1,
+        Ordering::Equal => sweep.plateau += 1,
+      }
+    }
+  }
+  sweep
+}
+
 pub fn part1(input: &[u32]) -> usize {
-  input
-    .windows(2)
-    .filter(|w| w[0] < w[1])
-    .count()
+  sweep(input.iter().copied(), 1).increasing
 }
 
 pub fn part2(input: &[u32]) -> usize {
-  let windows: Vec<u32> = input
-    .windows(3)
-    .map(|w| w.iter().sum())
-    .collect();
-
-  windows
-    .windows(2)
-    .filter(|w| w[0] < w[1])
-    .count()
-}
\ No newline at end of file
+  sweep(input.iter().copied(), 3).increasing
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const EXAMPLE: [u32; 10] = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+  #[test]
+  fn example() {
+    assert_eq!(part1(&EXAMPLE), 7);
+    assert_eq!(part2(&EXAMPLE), 5);
+  }
+
+  #[test]
+  fn sweep_counts() {
+    // 3-sums: 607 618 618 617 647 716 769 792
+    assert_eq!(sweep(EXAMPLE, 3), Sweep { increasing: 5, decreasing: 1, plateau: 1 });
+    assert_eq!(sweep(EXAMPLE, 10), Sweep::default());
+    assert_eq!(sweep((0..1_000_000).map(|i| i % 7), 7), Sweep { increasing: 0, decreasing: 0, plateau: 999_993 });
+  }
+}
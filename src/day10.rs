@@ -1,77 +1,249 @@
-fn error_score(c: &char) -> u64 {
-    match c {
-        ')' => 3,
-        ']' => 57,
-        '}' => 1197,
-        '>' => 25137,
-        _   => 0,
-    }
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pair {
+    pub open: char,
+    pub close: char,
+    // points for finding `close` where it doesn't belong
+    pub corruption_score: u64,
+    // points per `close` added to complete a line
+    pub completion_score: u64,
 }
 
-fn complete_score(c: &char) -> u64 {
-    match c {
-        '(' => 1,
-        '[' => 2,
-        '{' => 3,
-        '<' => 4,
-        _   => 0,
-    }
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Check {
+    Valid,
+    // position is the 0-based char index; expected is None when nothing was open
+    Corrupted { position: usize, expected: Option<char>, found: char },
+    // the closers that would make the line valid, in order
+    Incomplete { completion: String },
 }
 
-fn complete_score_string(s: &[char]) -> u64 {
-    let mut score = 0;
-    for c in s.iter().rev() {
-        print!("{} ", c);
-        score = score * 5 + complete_score(c);
+#[derive(Debug, PartialEq, Eq)]
+pub struct OverlappingPairs(pub char);
+
+// Nested brackets made of any set of pairs, where a pair may open and close with
+// the same character (like quotes) but no character belongs to two pairs.
+// Characters that aren't part of a pair are skipped.
+#[derive(Clone, Debug)]
+pub struct BracketSyntax {
+    pairs: Vec<Pair>,
+    closers: HashMap<char, char>,
+    openers: HashMap<char, char>,
+}
+
+impl BracketSyntax {
+    pub fn new(pairs: Vec<Pair>) -> Result<Self, OverlappingPairs> {
+        let mut closers = HashMap::new();
+        let mut openers = HashMap::new();
+        for pair in &pairs {
+            if closers.contains_key(&pair.open) || openers.contains_key(&pair.open) {
+                return Err(OverlappingPairs(pair.open));
+            }
+            if closers.contains_key(&pair.close) || openers.contains_key(&pair.close) {
+                return Err(OverlappingPairs(pair.close));
+            }
+            closers.insert(pair.open, pair.close);
+            openers.insert(pair.close, pair.open);
+        }
+        Ok(BracketSyntax { pairs, closers, openers })
+    }
+
+    // the chunks of the navigation subsystem, scored as in the puzzle
+    pub fn navigation() -> Self {
+        BracketSyntax::new(vec![
+            Pair { open: '(', close: ')', corruption_score: 3, completion_score: 1 },
+            Pair { open: '[', close: ']', corruption_score: 57, completion_score: 2 },
+            Pair { open: '{', close: '}', corruption_score: 1197, completion_score: 3 },
+            Pair { open: '<', close: '>', corruption_score: 25137, completion_score: 4 },
+        ]).unwrap()
+    }
+
+    pub fn check(&self, line: &str) -> Check {
+        let mut stack = Vec::new();
+        for (position, c) in line.chars().enumerate() {
+            // closing comes first, for pairs that open and close with the same character
+            if stack.last() == Some(&c) {
+                stack.pop();
+            } else if let Some(&close) = self.closers.get(&c) {
+                stack.push(close);
+            } else if self.openers.contains_key(&c) {
+                return Check::Corrupted { position, expected: stack.last().copied(), found: c };
+            }
+        }
+        if stack.is_empty() {
+            Check::Valid
+        } else {
+            Check::Incomplete { completion: stack.iter().rev().collect() }
+        }
+    }
+
+    fn pair(&self, close: char) -> Option<&Pair> {
+        self.pairs.iter().find(|pair| pair.close == close)
+    }
+
+    // points for the first illegal character of a corrupted line, else 0
+    pub fn corruption_score(&self, check: &Check) -> u64 {
+        match check {
+            Check::Corrupted { found, .. } => self.pair(*found).map_or(0, |pair| pair.corruption_score),
+            _ => 0,
+        }
+    }
+
+    // each closer multiplies the score so far by 5 and adds its points
+    pub fn completion_score(&self, completion: &str) -> u64 {
+        completion
+            .chars()
+            .fold(0, |score, c| score * 5 + self.pair(c).map_or(0, |pair| pair.completion_score))
     }
-    score
 }
 
-fn check_corrupt(line: &str) -> Result<Vec<char>, char> {
-    let mut stack = Vec::new();
-    for char in line.chars() {
-        match char {
-            ')' => if stack.pop() != Some('(') {
-                return Err(char);
-            },
-            ']' => if stack.pop() != Some('[') {
-                return Err(char);
+// A problem on one line of input. Lines and columns count from 1, as editors show them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    Illegal { line: usize, column: usize, expected: Option<char>, found: char },
+    // column is just past the end of the line
+    Incomplete { line: usize, column: usize, completion: String },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Illegal { line, column, expected: Some(expected), found } => {
+                write!(f, "{}:{}: error: expected '{}', found '{}'", line, column, expected, found)
             },
-            '}' => if stack.pop() != Some('{') {
-                return Err(char);
+            Diagnostic::Illegal { line, column, expected: None, found } => {
+                write!(f, "{}:{}: error: unexpected '{}' with nothing open", line, column, found)
             },
-            '>' => if stack.pop() != Some('<') {
-                return Err(char);
+            Diagnostic::Incomplete { line, column, completion } => {
+                write!(f, "{}:{}: warning: incomplete line, missing '{}'", line, column, completion)
             },
-            _ => stack.push(char),
         }
     }
-    Ok(stack)
+}
+
+// Diagnostics for every line that isn't valid, in input order.
+pub fn diagnostics(syntax: &BracketSyntax, lines: &[String]) -> Vec<Diagnostic> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, text)| match syntax.check(text) {
+            Check::Valid => None,
+            Check::Corrupted { position, expected, found } => {
+                Some(Diagnostic::Illegal { line: idx + 1, column: position + 1, expected, found })
+            },
+            Check::Incomplete { completion } => {
+                Some(Diagnostic::Incomplete { line: idx + 1, column: text.chars().count() + 1, completion })
+            },
+        })
+        .collect()
 }
 
 pub fn part1(lines: &[String]) -> u64 {
-    let mut errors: Vec<char> = Vec::new();
-    for line in lines {
-        match check_corrupt(line) {
-            Err(corruption) => errors.push(corruption),
-            _ => (),
-        }
-    }
-    errors.iter().map(error_score).sum()
+    let syntax = BracketSyntax::navigation();
+    lines.iter().map(|line| syntax.corruption_score(&syntax.check(line))).sum()
 }
 
 pub fn part2(lines: &[String]) -> u64 {
+    let syntax = BracketSyntax::navigation();
     let mut complete_score: Vec<u64> = Vec::new();
     for line in lines {
-        match check_corrupt(line) {
-            Ok(stack) => {
-                let score = complete_score_string(&stack);
-                println!("{}", score);
-                complete_score.push(score);
-            },
-            _ => (),
+        if let Check::Incomplete { completion } = syntax.check(line) {
+            complete_score.push(syntax.completion_score(&completion));
         }
     }
+    // 0 when no line is incomplete
     complete_score.sort_unstable();
-    complete_score[complete_score.len() / 2]
+    complete_score.get(complete_score.len() / 2).copied().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diagnostics, BracketSyntax, Check, Diagnostic, OverlappingPairs, Pair};
+
+    fn example() -> Vec<String> {
+        include_str!("input/day10_example.txt").lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn example_scores() {
+        assert_eq!(super::part1(&example()), 26397);
+        assert_eq!(super::part2(&example()), 288957);
+    }
+
+    #[test]
+    fn checks() {
+        let syntax = BracketSyntax::navigation();
+        assert_eq!(syntax.check("[<>({}){}[([])<>]]"), Check::Valid);
+        assert_eq!(
+            syntax.check("{([(<{}[<>[]}>{[]{[(<()>"),
+            Check::Corrupted { position: 12, expected: Some(']'), found: '}' }
+        );
+        assert_eq!(syntax.check("())"), Check::Corrupted { position: 2, expected: None, found: ')' });
+        let incomplete = syntax.check("[({(<(())[]>[[{[]{<()<>>");
+        assert_eq!(incomplete, Check::Incomplete { completion: "}}]])})]".to_string() });
+        if let Check::Incomplete { completion } = incomplete {
+            assert_eq!(syntax.completion_score(&completion), 288957);
+        }
+    }
+
+    #[test]
+    fn custom_pairs() {
+        // any characters can pair up, and everything else is skipped
+        let syntax = BracketSyntax::new(vec![
+            Pair { open: '«', close: '»', corruption_score: 1, completion_score: 1 },
+            Pair { open: '(', close: ')', corruption_score: 2, completion_score: 2 },
+        ]).unwrap();
+        assert_eq!(syntax.check("say «hi (there)»"), Check::Valid);
+        assert_eq!(syntax.check("«(»"), Check::Corrupted { position: 2, expected: Some(')'), found: '»' });
+        assert_eq!(syntax.check("((«"), Check::Incomplete { completion: "»))".to_string() });
+        assert_eq!(syntax.completion_score("»))"), 25 + 2 * 5 + 2);
+        assert_eq!(syntax.corruption_score(&syntax.check("«)")), 2);
+    }
+
+    #[test]
+    fn symmetric_pairs() {
+        let syntax = BracketSyntax::new(vec![
+            Pair { open: '"', close: '"', corruption_score: 1, completion_score: 1 },
+            Pair { open: '[', close: ']', corruption_score: 2, completion_score: 2 },
+        ]).unwrap();
+        assert_eq!(syntax.check("\"a\""), Check::Valid);
+        assert_eq!(syntax.check("[\"a\", \"b\"]"), Check::Valid);
+        // inside a quote another quote closes it, so this one opens a new one
+        assert_eq!(syntax.check("[\"a\"\""), Check::Incomplete { completion: "\"]".to_string() });
+        assert_eq!(syntax.check("\"[\""), Check::Incomplete { completion: "\"]\"".to_string() });
+        assert_eq!(syntax.check("\"a]"), Check::Corrupted { position: 2, expected: Some('"'), found: ']' });
+    }
+
+    #[test]
+    fn overlapping_pairs() {
+        let pair = |open, close| Pair { open, close, corruption_score: 0, completion_score: 0 };
+        assert_eq!(BracketSyntax::new(vec![pair('(', ')'), pair('(', ']')]).err(), Some(OverlappingPairs('(')));
+        assert_eq!(BracketSyntax::new(vec![pair('(', ')'), pair('[', ')')]).err(), Some(OverlappingPairs(')')));
+        assert_eq!(BracketSyntax::new(vec![pair('(', ')'), pair(')', '(')]).err(), Some(OverlappingPairs(')')));
+        assert_eq!(BracketSyntax::new(vec![pair('|', '|'), pair('(', '|')]).err(), Some(OverlappingPairs('|')));
+    }
+
+    #[test]
+    fn example_diagnostics() {
+        let found = diagnostics(&BracketSyntax::navigation(), &example());
+        assert_eq!(found.len(), 10);
+        assert_eq!(
+            found[0],
+            Diagnostic::Incomplete { line: 1, column: 25, completion: "}}]])})]".to_string() }
+        );
+        assert_eq!(found[2], Diagnostic::Illegal { line: 3, column: 13, expected: Some(']'), found: '}' });
+        assert_eq!(found[0].to_string(), "1:25: warning: incomplete line, missing '}}]])})]'");
+        assert_eq!(found[2].to_string(), "3:13: error: expected ']', found '}'");
+        let unmatched = diagnostics(&BracketSyntax::navigation(), &["<>>".to_string(), "()".to_string()]);
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].to_string(), "1:3: error: unexpected '>' with nothing open");
+    }
+
+    #[test]
+    fn no_incomplete_lines() {
+        assert_eq!(super::part2(&["{()}".to_string(), "(]".to_string()]), 0);
+        assert_eq!(super::part2(&[]), 0);
+    }
 }
@@ -1,55 +1,230 @@
 use std::collections::HashMap;
 use itertools::{Itertools, MinMaxResult};
 
-fn simulation(input: &str, count: usize) -> u64 {
-    let mut input = input.lines();
-    let s: Vec<char> = input.next().unwrap().chars().collect();
-    let first = s[0];
-    let last = s[s.len()-1];
-
-    let mut rules: HashMap<(char, char), char> = HashMap::new();
-    for rule in input {
-        let rule = rule.trim();
-        if !rule.is_empty() {
-            let (from, to) = rule.split_once(" -> ").unwrap();
-            let key: Vec<char> = from.chars().collect();
-            let val: char = to.chars().next().unwrap();
-            rules.insert((key[0], key[1]), val);
+use crate::matrix::{mul_matrix, mul_vector, Arithmetic, Matrix, Overflow};
+
+// A template and its insertion rules, compiled into a transition matrix over
+// element pairs: column `from` counts the pairs `from` turns into in one step.
+pub struct Polymer {
+    elements: Vec<char>,
+    template: Vec<usize>,
+    // the element inserted into each pair, if any
+    insertions: Vec<Option<usize>>,
+    transitions: Matrix,
+}
+
+impl Polymer {
+    pub fn parse(input: &str) -> Self {
+        let mut input = input.lines();
+        let template = input.next().unwrap().trim();
+
+        let mut rules: HashMap<(char, char), char> = HashMap::new();
+        for rule in input {
+            let rule = rule.trim();
+            if !rule.is_empty() {
+                let (from, to) = rule.split_once(" -> ").unwrap();
+                let key: Vec<char> = from.chars().collect();
+                let val: char = to.chars().next().unwrap();
+                rules.insert((key[0], key[1]), val);
+            }
+        }
+        Polymer::new(template, rules)
+    }
+
+    // Any rewriting system where a symbol is inserted between each matching pair.
+    pub fn new(template: &str, rules: HashMap<(char, char), char>) -> Self {
+        let template: Vec<char> = template.chars().collect();
+        let elements: Vec<char> = template
+            .iter()
+            .copied()
+            .chain(rules.iter().flat_map(|(&(a, b), &c)| [a, b, c]))
+            .sorted()
+            .dedup()
+            .collect();
+        let index = |c: char| elements.binary_search(&c).unwrap();
+        let n = elements.len();
+        let pair = |a: usize, b: usize| a * n + b;
+
+        let mut insertions = vec![None; n * n];
+        let mut transitions = vec![vec![0; n * n]; n * n];
+        for a in 0..n {
+            for b in 0..n {
+                match rules.get(&(elements[a], elements[b])) {
+                    Some(&insert) => {
+                        let c = index(insert);
+                        insertions[pair(a, b)] = Some(c);
+                        transitions[pair(a, c)][pair(a, b)] += 1;
+                        transitions[pair(c, b)][pair(a, b)] += 1;
+                    },
+                    None => transitions[pair(a, b)][pair(a, b)] += 1,
+                }
+            }
         }
+
+        let template = template.into_iter().map(index).collect();
+        Polymer { elements, template, insertions, transitions }
     }
 
-    let mut state: HashMap<(char, char), usize> = HashMap::new();
-    for idx in 0..s.len()-1 {
-        let pair = (s[idx], s[idx+1]);
-        *state.entry(pair).or_default() += 1;
+    // pair counts after `steps`, by exponentiation by squaring of the transition matrix
+    fn pair_vector(&self, mut steps: u64, arithmetic: Arithmetic) -> Result<Vec<u64>, Overflow> {
+        let n = self.elements.len();
+        let mut counts = vec![0; n * n];
+        for window in self.template.windows(2) {
+            let pair = window[0] * n + window[1];
+            counts[pair] = arithmetic.add(counts[pair], 1)?;
+        }
+
+        let mut power = self.transitions.clone();
+        while steps > 0 {
+            if steps & 1 == 1 {
+                counts = mul_vector(&power, &counts, arithmetic)?;
+            }
+            steps >>= 1;
+            if steps > 0 {
+                power = mul_matrix(&power, &power, arithmetic)?;
+            }
+        }
+        Ok(counts)
     }
 
-    for _ in 0..count {
-        let mut new_state: HashMap<(char, char), usize> = HashMap::new();
-        for ((a, b), count) in state {
-            if let Some(insert) = rules.get(&(a, b)) {
-                *new_state.entry((a, *insert)).or_default() += count;
-                *new_state.entry((*insert, b)).or_default() += count;
-            } else {
-                *new_state.entry((a, b)).or_default() += count;
+    // Every element but the last starts exactly one pair, and the last never changes.
+    pub fn element_counts_with(&self, steps: u64, arithmetic: Arithmetic) -> Result<HashMap<char, u64>, Overflow> {
+        let n = self.elements.len();
+        let mut counts: HashMap<char, u64> = HashMap::new();
+        if let Some(&last) = self.template.last() {
+            counts.insert(self.elements[last], arithmetic.add(0, 1)?);
+        }
+        for (pair, count) in self.pair_vector(steps, arithmetic)?.into_iter().enumerate() {
+            if count > 0 {
+                let entry = counts.entry(self.elements[pair / n]).or_default();
+                *entry = arithmetic.add(*entry, count)?;
             }
         }
-        state = new_state;
+        Ok(counts)
+    }
 
+    pub fn element_counts(&self, steps: u64) -> Result<HashMap<char, u64>, Overflow> {
+        self.element_counts_with(steps, Arithmetic::Checked)
     }
 
-    let mut freqs: HashMap<char, usize> = HashMap::new();
-    for ((a, b), count) in &state {
-        *freqs.entry(*a).or_default() += count;
-        *freqs.entry(*b).or_default() += count;
+    pub fn pair_counts_with(&self, steps: u64, arithmetic: Arithmetic) -> Result<HashMap<(char, char), u64>, Overflow> {
+        let n = self.elements.len();
+        Ok(self.pair_vector(steps, arithmetic)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(pair, count)| ((self.elements[pair / n], self.elements[pair % n]), count))
+            .collect())
     }
-    *freqs.entry(first).or_default() += 1;
-    *freqs.entry(last).or_default() += 1;
-    for val in freqs.values_mut() {
-        *val /= 2;
+
+    pub fn pair_counts(&self, steps: u64) -> Result<HashMap<(char, char), u64>, Overflow> {
+        self.pair_counts_with(steps, Arithmetic::Checked)
+    }
+
+    // The polymer after `steps`, one element at a time, so `.take(k)` gives a
+    // prefix without building the rest. Runs of left descents are kept as one
+    // pending entry, so even trillions of steps only cost what is taken.
+    pub fn chars(&self, steps: u64) -> Chars<'_> {
+        // pending pairs, the next one to expand on top
+        let stack = self.template
+            .windows(2)
+            .rev()
+            .map(|window| Pending::Pair(window[0], window[1], steps))
+            .collect();
+        Chars { polymer: self, first: self.template.first().copied(), stack }
     }
+
+    // Descending into the left half of (a, b) keeps a and replaces b with what
+    // is inserted, so the right elements b_0 = b, b_1, ... repeat with a period
+    // of at most n. Returns them up to the first repeat (and where the cycle
+    // starts), along with how deep the descent goes within `steps`.
+    fn descend(&self, a: usize, b: usize, steps: u64) -> (Descent, u64) {
+        let n = self.elements.len();
+        let mut seen = vec![None; n];
+        let mut values = vec![b];
+        seen[b] = Some(0);
+        loop {
+            let depth = values.len() - 1;
+            if depth as u64 == steps {
+                return (Descent { values, cycle_start: None }, steps);
+            }
+            match self.insertions[a * n + values[depth]] {
+                None => return (Descent { values, cycle_start: None }, depth as u64),
+                Some(c) => match seen[c] {
+                    Some(start) => return (Descent { values, cycle_start: Some(start) }, steps),
+                    None => {
+                        seen[c] = Some(values.len());
+                        values.push(c);
+                    },
+                },
+            }
+        }
+    }
+}
+
+struct Descent {
+    values: Vec<usize>,
+    cycle_start: Option<usize>,
+}
+
+impl Descent {
+    // b_k
+    fn get(&self, k: u64) -> usize {
+        match self.cycle_start {
+            Some(start) if k >= self.values.len() as u64 => {
+                let period = (self.values.len() - start) as u64;
+                self.values[start + ((k - start as u64) % period) as usize]
+            },
+            _ => self.values[k as usize],
+        }
+    }
+}
+
+enum Pending {
+    // emit what grows between a and b in `steps`, then b itself
+    Pair(usize, usize, u64),
+    // the right halves left behind by a descent of `steps`: for k from depth - 1
+    // down to 0, Pair(b_{k+1}, b_k, steps - k - 1)
+    Descent { descent: Descent, steps: u64, depth: u64 },
+}
+
+pub struct Chars<'a> {
+    polymer: &'a Polymer,
+    first: Option<usize>,
+    stack: Vec<Pending>,
+}
+
+impl Iterator for Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(self.polymer.elements[first]);
+        }
+        let (a, b, steps) = match self.stack.pop()? {
+            Pending::Pair(a, b, steps) => (a, b, steps),
+            Pending::Descent { descent, steps, depth } => {
+                let k = depth - 1;
+                let pair = (descent.get(k + 1), descent.get(k), steps - k - 1);
+                if k > 0 {
+                    self.stack.push(Pending::Descent { descent, steps, depth: k });
+                }
+                pair
+            },
+        };
+        let (descent, depth) = self.polymer.descend(a, b, steps);
+        let last = descent.get(depth);
+        if depth > 0 {
+            self.stack.push(Pending::Descent { descent, steps, depth });
+        }
+        Some(self.polymer.elements[last])
+    }
+}
+
+fn simulation(input: &str, count: u64) -> u64 {
+    let freqs = Polymer::parse(input).element_counts(count).unwrap();
     match freqs.values().minmax() {
-        MinMaxResult::MinMax(a, b) => (*b-*a) as u64,
+        MinMaxResult::MinMax(a, b) => *b - *a,
         _ => 0
     }
 }
@@ -118,4 +293,138 @@ CN -> C"
             ), 2188189693529
         )
     }
+
+    const EXAMPLE: &str = "\
+NNCB
+
+CH -> B
+HH -> N
+CB -> H
+NH -> C
+HB -> C
+HC -> B
+HN -> C
+NN -> C
+BH -> H
+NC -> B
+NB -> B
+BN -> B
+BB -> N
+BC -> B
+CC -> N
+CN -> C";
+
+    #[test]
+    fn element_counts() {
+        use std::collections::HashMap;
+
+        let polymer = super::Polymer::parse(EXAMPLE);
+        assert_eq!(
+            polymer.element_counts(0),
+            Ok(HashMap::from([('N', 2), ('C', 1), ('B', 1)]))
+        );
+        assert_eq!(
+            polymer.element_counts(10),
+            Ok(HashMap::from([('B', 1749), ('C', 298), ('H', 161), ('N', 865)]))
+        );
+        assert_eq!(polymer.element_counts(100), Err(super::Overflow));
+    }
+
+    #[test]
+    fn modular_element_counts() {
+        use std::num::NonZeroU64;
+        use super::Arithmetic;
+
+        const P: u64 = 1_000_000_007;
+        let modulus = Arithmetic::Modular(NonZeroU64::new(P).unwrap());
+        let polymer = super::Polymer::parse(EXAMPLE);
+        let exact = polymer.element_counts(40).unwrap();
+        let modular = polymer.element_counts_with(40, modulus).unwrap();
+        for (element, count) in exact {
+            assert_eq!(modular[&element], count % P);
+        }
+
+        // the polymer has 3 * 2^n + 1 elements after n steps
+        let steps: u64 = 1_000_000_000_000;
+        let (mut length, mut base, mut e) = (1, 2, steps);
+        while e > 0 {
+            if e & 1 == 1 {
+                length = length * base % P;
+            }
+            base = base * base % P;
+            e >>= 1;
+        }
+        let length = (3 * length + 1) % P;
+        let counts = polymer.element_counts_with(steps, modulus).unwrap();
+        assert_eq!(counts.values().sum::<u64>() % P, length);
+    }
+
+    #[test]
+    fn polymer_prefix() {
+        let polymer = super::Polymer::parse(EXAMPLE);
+        assert_eq!(polymer.chars(0).collect::<String>(), "NNCB");
+        assert_eq!(polymer.chars(1).collect::<String>(), "NCNBCHB");
+        assert_eq!(polymer.chars(2).collect::<String>(), "NBCCNBBBCBHCB");
+        assert_eq!(
+            polymer.chars(4).collect::<String>(),
+            "NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB"
+        );
+        assert_eq!(polymer.chars(10).count(), 3073);
+        assert_eq!(polymer.chars(40).take(8).collect::<String>(), "NBBNBBNB");
+        // NB -> B keeps the left edge the same from a few steps in
+        assert_eq!(
+            polymer.chars(1_000_000_000_000).take(1000).collect::<String>(),
+            polymer.chars(40).take(1000).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn polymer_prefix_matches_expansion() {
+        use std::collections::HashMap;
+
+        // the left edge of AB alternates between AB and AC
+        let rules = HashMap::from([(('A', 'B'), 'C'), (('A', 'C'), 'B'), (('C', 'B'), 'A'), (('B', 'A'), 'A')]);
+        let example_rules: HashMap<(char, char), char> = EXAMPLE
+            .lines()
+            .skip(2)
+            .map(|rule| {
+                let (from, to) = rule.split_once(" -> ").unwrap();
+                let from: Vec<char> = from.chars().collect();
+                ((from[0], from[1]), to.chars().next().unwrap())
+            })
+            .collect();
+        for (template, rules) in [("AB", rules), ("NNCB", example_rules)] {
+            let polymer = super::Polymer::new(template, rules.clone());
+            let mut expanded: Vec<char> = template.chars().collect();
+            for steps in 0..=10 {
+                assert_eq!(polymer.chars(steps).collect::<Vec<_>>(), expanded, "{} after {} steps", template, steps);
+                let mut next = vec![expanded[0]];
+                for pair in expanded.windows(2) {
+                    next.extend(rules.get(&(pair[0], pair[1])));
+                    next.push(pair[1]);
+                }
+                expanded = next;
+            }
+        }
+        let polymer = super::Polymer::new("AB", HashMap::from([(('A', 'B'), 'C'), (('A', 'C'), 'B')]));
+        assert_eq!(polymer.chars(1_000_000_000_000).take(3).collect::<String>(), "ABC");
+        assert_eq!(polymer.chars(1_000_000_000_001).take(3).collect::<String>(), "ACB");
+    }
+
+    #[test]
+    fn pair_counts() {
+        use std::collections::HashMap;
+
+        let polymer = super::Polymer::parse(EXAMPLE);
+        assert_eq!(
+            polymer.pair_counts(1),
+            Ok(HashMap::from([(('N', 'C'), 1), (('C', 'N'), 1), (('N', 'B'), 1), (('B', 'C'), 1), (('C', 'H'), 1), (('H', 'B'), 1)]))
+        );
+
+        let mut from_chars: HashMap<char, u64> = HashMap::new();
+        for c in polymer.chars(10) {
+            *from_chars.entry(c).or_default() += 1;
+        }
+        assert_eq!(polymer.element_counts(10), Ok(from_chars));
+    }
 }
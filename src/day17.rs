@@ -1,76 +1,292 @@
-fn hit_target(x_min: i64, x_max: i64, y_min: i64, y_max: i64, mut dx: i64, mut dy: i64) -> Option<i64> {
-    let (mut x, mut y) = (0, 0);
-    let mut top_y = 0;
-    loop {
-        x += dx;
-        y += dy;
-        top_y = top_y.max(y);
-
-        if x_min <= x && x <= x_max && y_min <= y && y <= y_max {
-            return Some(top_y)
-        }
+use std::str::FromStr;
 
-        if y < y_min || x > x_max {
-            return None
-        }
+// position after n steps along an axis whose velocity starts at v and drops by 1 each step
+fn displacement(v: i64, n: i64) -> i64 {
+    n * v - n * (n - 1) / 2
+}
 
-        if dx > 0 {
-            dx -= 1;
-        } else if dx < 0 {
-            dx += 1;
-        }
-        dy -= 1;
+// smallest n in 0..=v with displacement(v, n) >= bound, if the peak reaches it
+fn rising_step(v: i64, bound: i64) -> Option<i64> {
+    if bound <= 0 {
+        return Some(0);
+    }
+    if v <= 0 || displacement(v, v) < bound {
+        return None;
     }
+    // root of n^2 - (2v + 1)n + 2 * bound = 0, nudged to correct float rounding
+    let b = (2 * v + 1) as f64;
+    let mut n = ((b - (b * b - 8.0 * bound as f64).sqrt()) / 2.0).ceil() as i64;
+    n = n.clamp(0, v);
+    while n > 0 && displacement(v, n - 1) >= bound {
+        n -= 1;
+    }
+    while displacement(v, n) < bound {
+        n += 1;
+    }
+    Some(n)
 }
 
-pub fn part1(input: &str) -> i64 {
-    let (x_bounds, y_bounds) = input.trim_start_matches("target area: ").split_once(',').unwrap();
-    let (x_min, x_max) = x_bounds.trim().trim_start_matches("x=").split_once("..").unwrap();
-    let (y_min, y_max) = y_bounds.trim().trim_start_matches("y=").split_once("..").unwrap();
-    let x_min: i64 = x_min.parse().unwrap();
-    let x_max: i64 = x_max.parse().unwrap();
-    let y_min: i64 = y_min.parse().unwrap();
-    let y_max: i64 = y_max.parse().unwrap();
-    println!("{} {} {} {}", x_min, x_max, y_min, y_max);
-
-    let y_bound = y_min.abs().max(y_max.abs());
-
-    let mut ans = 0;
-    for dx in 0..=x_max {
-        for dy in -y_bound..=y_bound {
-            if let Some(top_y) = hit_target(x_min, x_max, y_min, y_max, dx, dy) {
-                ans = ans.max(top_y);
-            }
-        }
+// largest n past the peak with displacement(v, n) >= bound, if any
+fn falling_step(v: i64, bound: i64) -> Option<i64> {
+    let top = v.max(0);
+    if displacement(v, top) < bound {
+        return None;
     }
-    ans
+    let b = (2 * v + 1) as f64;
+    let mut n = ((b + (b * b - 8.0 * bound as f64).sqrt()) / 2.0).floor() as i64;
+    n = n.max(top);
+    while displacement(v, n + 1) >= bound {
+        n += 1;
+    }
+    while displacement(v, n) < bound {
+        n -= 1;
+    }
+    Some(n)
 }
 
-pub fn part2(input: &str) -> i64 {
-    let (x_bounds, y_bounds) = input.trim_start_matches("target area: ").split_once(',').unwrap();
-    let (x_min, x_max) = x_bounds.trim().trim_start_matches("x=").split_once("..").unwrap();
-    let (y_min, y_max) = y_bounds.trim().trim_start_matches("y=").split_once("..").unwrap();
-    let x_min: i64 = x_min.parse().unwrap();
-    let x_max: i64 = x_max.parse().unwrap();
-    let y_min: i64 = y_min.parse().unwrap();
-    let y_max: i64 = y_max.parse().unwrap();
-    println!("{} {} {} {}", x_min, x_max, y_min, y_max);
-
-    let y_bound = y_min.abs().max(y_max.abs());
-
-    let mut ans = 0;
-    for dx in 0..=x_max {
-        for dy in -y_bound..=y_bound {
-            if hit_target(x_min, x_max, y_min, y_max, dx, dy).is_some() {
-                ans += 1;
+// Steps (inclusive, from 1) at which x is inside the target. Drag stops the probe
+// after |dx| steps, so the range is open-ended (i64::MAX) if it stops inside.
+fn x_steps(dx: i64, x_min: i64, x_max: i64) -> Option<(i64, i64)> {
+    if dx < 0 {
+        return x_steps(-dx, -x_max, -x_min);
+    }
+    let first = rising_step(dx, x_min)?.max(1);
+    let last = match rising_step(dx, x_max + 1) {
+        Some(n) => n - 1,
+        None => i64::MAX,
+    };
+    (first <= last).then_some((first, last))
+}
+
+// Steps at which y is inside the target: at most two ranges, one on the way up
+// and one on the way down.
+fn y_steps(dy: i64, y_min: i64, y_max: i64) -> Vec<(i64, i64)> {
+    let (first, last) = match (rising_step(dy, y_min), falling_step(dy, y_min)) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Vec::new(),
+    };
+    let ranges = match (rising_step(dy, y_max + 1), falling_step(dy, y_max + 1)) {
+        (Some(above_first), Some(above_last)) => vec![(first, above_first - 1), (above_last + 1, last)],
+        _ => vec![(first, last)],
+    };
+    ranges
+        .into_iter()
+        .map(|(lo, hi)| (lo.max(1), hi))
+        .filter(|(lo, hi)| lo <= hi)
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TargetArea {
+    pub x_min: i64,
+    pub x_max: i64,
+    pub y_min: i64,
+    pub y_max: i64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingPrefix,
+    MissingAxis(char),
+    InvalidNumber(String),
+    SwappedRange { axis: char, min: i64, max: i64 },
+}
+
+fn parse_range(s: &str, axis: char) -> Result<(i64, i64), ParseError> {
+    let (min, max) = s
+        .trim()
+        .strip_prefix(axis)
+        .and_then(|s| s.strip_prefix('='))
+        .and_then(|s| s.split_once(".."))
+        .ok_or(ParseError::MissingAxis(axis))?;
+    let parse = |n: &str| n.trim().parse::<i64>().map_err(|_| ParseError::InvalidNumber(n.to_string()));
+    let (min, max) = (parse(min)?, parse(max)?);
+    if min > max {
+        return Err(ParseError::SwappedRange { axis, min, max });
+    }
+    Ok((min, max))
+}
+
+impl FromStr for TargetArea {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bounds = s.trim().strip_prefix("target area:").ok_or(ParseError::MissingPrefix)?;
+        let (x_bounds, y_bounds) = bounds.split_once(',').ok_or(ParseError::MissingAxis('y'))?;
+        let (x_min, x_max) = parse_range(x_bounds, 'x')?;
+        let (y_min, y_max) = parse_range(y_bounds, 'y')?;
+        Ok(TargetArea { x_min, x_max, y_min, y_max })
+    }
+}
+
+impl TargetArea {
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        self.x_min <= x && x <= self.x_max && self.y_min <= y && y <= self.y_max
+    }
+
+    // Every (dx, dy) that is inside the target after some step, or None if there are
+    // infinitely many: a probe stopping above or below the target with y = 0 inside
+    // it comes back to y = 0 whatever dy is.
+    pub fn valid_velocities(&self) -> Option<Vec<(i64, i64)>> {
+        let TargetArea { x_min, x_max, y_min, y_max } = *self;
+        let x_ranges: Vec<(i64, (i64, i64))> = (x_min.min(0)..=x_max.max(0))
+            .filter_map(|dx| x_steps(dx, x_min, x_max).map(|steps| (dx, steps)))
+            .collect();
+        if y_min <= 0 && 0 <= y_max && x_ranges.iter().any(|(_, (_, last))| *last == i64::MAX) {
+            return None;
+        }
+
+        // past this a probe overshoots on its first step down, or reaches y = 0 too late for x
+        let latest_step = x_ranges.iter().map(|(_, (_, last))| *last).filter(|last| *last != i64::MAX).max().unwrap_or(0);
+        let dy_bound = y_min.abs().max(y_max.abs()).max(latest_step);
+
+        let y_ranges: Vec<(i64, Vec<(i64, i64)>)> = (y_min.min(0)..=dy_bound)
+            .map(|dy| (dy, y_steps(dy, y_min, y_max)))
+            .filter(|(_, steps)| !steps.is_empty())
+            .collect();
+
+        let mut velocities = Vec::new();
+        for &(dx, (x_first, x_last)) in &x_ranges {
+            for (dy, steps) in &y_ranges {
+                if steps.iter().any(|&(lo, hi)| lo.max(x_first) <= hi.min(x_last)) {
+                    velocities.push((dx, *dy));
+                }
             }
         }
+        Some(velocities)
+    }
+}
+
+// Positions of a probe after each step, forever.
+#[derive(Copy, Clone, Debug)]
+pub struct Trajectory {
+    x: i64,
+    y: i64,
+    dx: i64,
+    dy: i64,
+}
+
+impl Trajectory {
+    pub fn new(dx: i64, dy: i64) -> Self {
+        Trajectory { x: 0, y: 0, dx, dy }
+    }
+}
+
+impl Iterator for Trajectory {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.x += self.dx;
+        self.y += self.dy;
+        self.dx -= self.dx.signum();
+        self.dy -= 1;
+        Some((self.x, self.y))
     }
-    ans
+}
+
+pub fn max_height(dy: i64) -> i64 {
+    displacement(dy, dy.max(0))
+}
+
+fn velocities(input: &str) -> Vec<(i64, i64)> {
+    let target: TargetArea = input.parse().unwrap();
+    target.valid_velocities().expect("infinitely many velocities hit the target")
+}
+
+pub fn part1(input: &str) -> i64 {
+    velocities(input)
+        .iter()
+        .map(|&(_, dy)| max_height(dy))
+        .max()
+        .unwrap_or(0)
+}
+
+pub fn part2(input: &str) -> i64 {
+    velocities(input).len() as i64
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{ParseError, TargetArea, Trajectory};
+
+    // step-by-step simulation, as a reference for the analytical solver
+    fn brute_force(target: &TargetArea) -> Vec<(i64, i64)> {
+        let TargetArea { x_min, x_max, y_min, y_max } = *target;
+        let bound = x_min.abs().max(x_max.abs()).max(y_min.abs()).max(y_max.abs());
+        let mut velocities = Vec::new();
+        for dx in x_min.min(0)..=x_max.max(0) {
+            for dy in -bound..=bound {
+                // by then the probe is below -bound for good
+                if Trajectory::new(dx, dy).take(2 * bound as usize + 3).any(|(x, y)| target.contains(x, y)) {
+                    velocities.push((dx, dy));
+                }
+            }
+        }
+        velocities
+    }
+
+    #[test]
+    fn analytical_matches_simulation() {
+        for (x_min, x_max, y_min, y_max) in [
+            (20, 30, -10, -5),
+            (-30, -20, -10, -5),
+            (20, 30, 5, 10),
+            (-30, -20, 5, 10),
+            (-5, 5, -10, -5),
+            (-5, 5, 3, 8),
+            (100, 102, -2, 2),
+            (56, 76, -162, -134),
+        ] {
+            let target = TargetArea { x_min, x_max, y_min, y_max };
+            assert_eq!(
+                target.valid_velocities(),
+                Some(brute_force(&target)),
+                "target x={}..{}, y={}..{}", x_min, x_max, y_min, y_max
+            );
+        }
+    }
+
+    #[test]
+    fn infinitely_many_velocities() {
+        // dx = 0 stays inside x, and every upward launch comes back to y = 0
+        assert_eq!(TargetArea { x_min: -5, x_max: 5, y_min: -5, y_max: 5 }.valid_velocities(), None);
+        assert_eq!(TargetArea { x_min: 3, x_max: 10, y_min: -2, y_max: 2 }.valid_velocities(), None);
+        // but nothing stops inside x=100..102
+        assert!(TargetArea { x_min: 100, x_max: 102, y_min: -2, y_max: 2 }.valid_velocities().is_some());
+    }
+
+    #[test]
+    fn parse_target_area() {
+        assert_eq!(
+            "target area: x=20..30, y=-10..-5".parse(),
+            Ok(TargetArea { x_min: 20, x_max: 30, y_min: -10, y_max: -5 })
+        );
+        assert_eq!(
+            include_str!("input/day17.txt").parse(),
+            Ok(TargetArea { x_min: 56, x_max: 76, y_min: -162, y_max: -134 })
+        );
+        assert_eq!("x=20..30, y=-10..-5".parse::<TargetArea>(), Err(ParseError::MissingPrefix));
+        assert_eq!("target area: x=20..30".parse::<TargetArea>(), Err(ParseError::MissingAxis('y')));
+        assert_eq!("target area: x=20..30, z=1..2".parse::<TargetArea>(), Err(ParseError::MissingAxis('y')));
+        assert_eq!(
+            "target area: x=20..3o, y=-10..-5".parse::<TargetArea>(),
+            Err(ParseError::InvalidNumber("3o".to_string()))
+        );
+        assert_eq!(
+            "target area: x=20..30, y=-5..-10".parse::<TargetArea>(),
+            Err(ParseError::SwappedRange { axis: 'y', min: -5, max: -10 })
+        );
+    }
+
+    #[test]
+    fn trajectory() {
+        // the 7,2 example from the puzzle
+        let path: Vec<_> = Trajectory::new(7, 2).take(7).collect();
+        assert_eq!(path, [(7, 2), (13, 3), (18, 3), (22, 2), (25, 0), (27, -3), (28, -7)]);
+        // drag stops at zero from either side
+        assert_eq!(Trajectory::new(-2, 0).nth(4).unwrap().0, -3);
+    }
+
     #[test]
     fn example1() {
         assert_eq!(
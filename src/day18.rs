@@ -1,9 +1,12 @@
+use std::cmp::Reverse;
 use std::collections::VecDeque;
+use std::thread;
 
 use itertools::{Itertools};
 
 
 
+#[derive(Clone)]
 enum Num {
     Pair(Box<Num>, Box<Num>),
     Literal(i64)
@@ -237,8 +240,42 @@ pub fn part1(input: &str) -> i64 {
     solve_magnitude(&input.lines().collect_vec())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BestPair {
+    pub left: usize,
+    pub right: usize,
+    pub magnitude: i64,
+}
+
+fn best_pair_in_rows(nums: &[Box<Num>], rows: impl Iterator<Item = usize>) -> Option<BestPair> {
+    rows.flat_map(|left| (0..nums.len()).filter(move |&right| right != left).map(move |right| (left, right)))
+        .map(|(left, right)| BestPair {
+            left,
+            right,
+            magnitude: add(nums[left].clone(), nums[right].clone()).magnitude(),
+        })
+        // ties go to the lowest (left, right) so the result doesn't depend on the thread count
+        .max_by_key(|pair| (pair.magnitude, Reverse((pair.left, pair.right))))
+}
+
+// Snailfish addition isn't commutative, so both (a, b) and (b, a) are tried.
+// Each worker takes every n-th left operand.
+pub fn best_pair(input: &str) -> Option<BestPair> {
+    let nums: Vec<Box<Num>> = input.lines().map(|line| Num::parse(&mut line.chars().collect())).collect();
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(nums.len()).max(1);
+    let nums = &nums;
+    thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|worker| scope.spawn(move || best_pair_in_rows(nums, (worker..nums.len()).step_by(workers))))
+            .collect_vec();
+        handles.into_iter()
+            .filter_map(|handle| handle.join().unwrap())
+            .max_by_key(|pair| (pair.magnitude, Reverse((pair.left, pair.right))))
+    })
+}
+
 pub fn part2(input: &str) -> i64 {
-    input.lines().tuple_combinations().map(|(a, b)| solve_magnitude(&[a, b])).max().unwrap()
+    best_pair(input).unwrap().magnitude
 }
 
 #[cfg(test)]
@@ -372,7 +409,26 @@ mod tests {
         assert_eq!(
             super::part2(
                 include_str!("input/day18.txt")
-            ), 1031
+            ), 4626
+        )
+    }
+
+    #[test]
+    fn example2_best_pair() {
+        let input = "\
+[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
+        assert_eq!(
+            best_pair(input),
+            Some(BestPair { left: 8, right: 0, magnitude: 3993 })
         )
     }
 }
@@ -1,9 +1,14 @@
+use std::array;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
 use std::ops::{Add, Mul, Sub};
 use itertools::{Itertools};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-struct Point([i64; 3]);
+pub const MIN_OVERLAP: usize = 12;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point(pub [i64; 3]);
 
 impl ToString for Point {
     fn to_string(&self) -> String {
@@ -21,6 +26,11 @@ impl Point {
         Point([0, 0, 0])
     }
 
+    fn squared_dist(&self, rhs: &Point) -> i64 {
+        let d = (*self - *rhs).0;
+        d[0] * d[0] + d[1] * d[1] + d[2] * d[2]
+    }
+
     fn manhattan_dist(&self, rhs: &Point) -> u64 {
         let a = self.0;
         let b = rhs.0;
@@ -62,170 +72,166 @@ impl Sub for Point {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-struct Matrix([[i64; 3]; 3]);
-
-impl Matrix {
-    fn zero() -> Self {
-        Matrix([[0; 3]; 3])
-    }
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rotation([[i64; 3]; 3]);
 
-    fn identity() -> Self { Matrix([[1, 0, 0], [0, 1, 0], [0, 0, 1]]) }
-}
+impl Rotation {
+    pub fn identity() -> Self { Rotation([[1, 0, 0], [0, 1, 0], [0, 0, 1]]) }
 
-impl Matrix {
     fn rows(&self) -> Vec<Point> {
         let rows = self.0;
         vec![Point(rows[0]), Point(rows[1]), Point(rows[2])]
     }
+
+    fn is_proper(&self) -> bool {
+        let rows = self.rows();
+        rows[0] * rows[1] == rows[2]
+    }
+
+    // try all signed permutations and filter out the reflections
+    pub fn all() -> Vec<Rotation> {
+        let mut ans = Vec::new();
+        for permutation in [0, 1, 2].iter().permutations(3) {
+            let x = *permutation[0];
+            let y = *permutation[1];
+            let z = *permutation[2];
+            for a in [-1, 1] {
+                for b in [-1, 1] {
+                    for c in [-1, 1] {
+                        let mut rotation = Rotation([[0; 3]; 3]);
+                        rotation.0[0][x] = a;
+                        rotation.0[1][y] = b;
+                        rotation.0[2][z] = c;
+                        if rotation.is_proper() {
+                            ans.push(rotation);
+                        }
+                    }
+                }
+            }
+        }
+        ans
+    }
+
+    // rotations are orthogonal, so the inverse is the transpose
+    pub fn inverse(&self) -> Self {
+        Rotation(array::from_fn(|i| array::from_fn(|j| self.0[j][i])))
+    }
 }
 
-impl Mul<Point> for Matrix {
+impl Mul<Point> for Rotation {
     type Output = Point;
     fn mul(self, rhs: Point) -> Self::Output {
-        let mut coordinates = [0, 0, 0];
-        for i in 0..3 {
-            for j in 0..3 {
-                coordinates[i] += self.0[i][j] * rhs.0[i];
-            }
-        }
-        Point(coordinates)
+        let p = rhs.0;
+        Point(self.0.map(|row| row[0] * p[0] + row[1] * p[1] + row[2] * p[2]))
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-struct Alignment {
-    orientation: [i64; 3],
-    translation: Point,
+impl Mul for Rotation {
+    type Output = Rotation;
+    /// Composition: `(a * b) * p == a * (b * p)`
+    fn mul(self, rhs: Rotation) -> Self::Output {
+        Rotation(array::from_fn(|i| array::from_fn(|j| (0..3).map(|k| self.0[i][k] * rhs.0[k][j]).sum())))
+    }
 }
 
-impl Alignment {
-    fn identity() -> Self {
-        let translation = Point::zero();
-        Alignment { orientation: [1, 2, 3], translation }
-    }
-
-    fn apply(&self, point: Point) -> Point {
-        orient(point, &self.orientation) + self.translation
-    }
-
-    fn from(a: &[Point], b: &[Point]) -> Option<Self> {
-        // find the transformation a to b
-        let mut max_score = 0;
-        for i in 0..a.len() {
-            for j in 0..b.len() {
-                // find the transformation that turn a[i] into b[j]
-                'orientation: for orientation in ORIENTATIONS {
-                    let translation = b[j] - orient(a[i], &orientation);
-                    let alignment = Alignment { orientation, translation };
-                    // find the score of this alignment
-                    let score = alignment.score(a, b);
-                    // println!("translation: {}", translation.to_string());
-                    max_score = max_score.max(score);
-                    if score >= 12 {
-                        return Some(alignment);
-                    }
+// Maps a scanner's own coordinates into its parent frame: `rotation` first, then move to `position`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Pose {
+    pub rotation: Rotation,
+    pub position: Point,
+}
+
+impl Pose {
+    pub fn identity() -> Self {
+        Pose { rotation: Rotation::identity(), position: Point::zero() }
+    }
+
+    pub fn apply(&self, point: Point) -> Point {
+        self.rotation * point + self.position
+    }
+
+    // `other` is expressed in this pose's frame, the result in this pose's parent frame
+    pub fn compose(&self, other: &Pose) -> Pose {
+        Pose { rotation: self.rotation * other.rotation, position: self.apply(other.position) }
+    }
+
+    pub fn inverse(&self) -> Pose {
+        let rotation = self.rotation.inverse();
+        Pose { rotation, position: Point::zero() - rotation * self.position }
+    }
+
+    fn from_fingerprints(a: &[Point], fa: &Fingerprint, b: &[Point], fb: &Fingerprint, min_overlap: usize) -> Option<Self> {
+        // find the transformation a to b, only trying beacons that look alike in both scanners
+        let b_set: HashSet<_> = b.iter().copied().collect();
+        let rotations = Rotation::all();
+        for (i, j) in fa.correspondences(fb, min_overlap) {
+            for rotation in &rotations {
+                let position = b[j] - *rotation * a[i];
+                let pose = Pose { rotation: *rotation, position };
+                if pose.score(a, &b_set) >= min_overlap {
+                    return Some(pose);
                 }
             }
         }
-        // println!("No alignment found, max score = {}", max_score);
         None
     }
 
-    fn score(&self, a: &[Point], b: &[Point]) -> usize {
-        let a: HashSet<_> = a.iter().map(|p| self.apply(*p)).collect();
-        let b: HashSet<_> = b.iter().copied().collect();
-        a.intersection(&b).count()
+    fn score(&self, a: &[Point], b: &HashSet<Point>) -> usize {
+        a.iter().filter(|p| b.contains(&self.apply(**p))).count()
     }
 }
 
-fn test_orientation(matrix: Matrix) -> bool {
-    let rows = matrix.rows();
-    rows[0] * rows[1] == rows[2]
+// Squared distances between every pair of beacons seen by one scanner. They don't change under
+// rotation or translation, so two scanners sharing n beacons share at least n * (n - 1) / 2 of them.
+struct Fingerprint {
+    distances: HashMap<i64, Vec<(usize, usize)>>,
 }
 
-const ORIENTATIONS: [[i64; 3]; 24] = [
-    [1, 2, 3],
-    [2, -1, 3],
-    [-1, -2, 3],
-    [-2, 1, 3],
-    [2, 3, 1],
-    [-1, 3, 2],
-    [-2, 3, -1],
-    [1, 3, -2],
-    [3, 1, 2],
-    [3, 2, -1],
-    [3, -1, -2],
-    [3, -2, 1],
-    [2, 1, -3],
-    [-1, 2, -3],
-    [-2, -1, -3],
-    [1, -2, -3],
-    [1, -3, 2],
-    [2, -3, -1],
-    [-1, -3, -2],
-    [-2, -3, 1],
-    [-3, 2, 1],
-    [-3, -1, 2],
-    [-3, -2, -1],
-    [-3, 1, -2],
-];
-
-fn orient(point: Point, orientation: &[i64; 3]) -> Point {
-    let mut result = [0, 0, 0];
-    let p = point.0;
-    for (write, read) in orientation.iter().enumerate() {
-        result[write] = p[read.abs() as usize - 1];
-        if *read < 0 {
-            result[write] = -result[write];
+impl Fingerprint {
+    fn of(points: &[Point]) -> Self {
+        let mut distances: HashMap<i64, Vec<(usize, usize)>> = HashMap::new();
+        for (i, j) in (0..points.len()).tuple_combinations() {
+            distances.entry(points[i].squared_dist(&points[j])).or_default().push((i, j));
         }
+        Fingerprint { distances }
+    }
+
+    fn shared(&self, other: &Fingerprint) -> usize {
+        self.distances
+            .iter()
+            .filter_map(|(d, pairs)| other.distances.get(d).map(|others| pairs.len().min(others.len())))
+            .sum()
     }
-    Point(result)
-}
 
-// try all combinations and filter out those illegal
-fn all_orientations() -> Vec<Matrix> {
-    let mut ans = Vec::new();
-    for permutation in [0, 1, 2].iter().permutations(3) {
-        let x = *permutation[0];
-        let y = *permutation[1];
-        let z = *permutation[2];
-        for a in [-1, 1] {
-            for b in [-1, 1] {
-                for c in [-1, 1] {
-                    let mut matrix = Matrix::zero();
-                    matrix.0[0][x] = a;
-                    matrix.0[1][y] = b;
-                    matrix.0[2][z] = c;
-                    if test_orientation(matrix) {
-                        ans.push(matrix);
+    fn may_overlap(&self, other: &Fingerprint, min_overlap: usize) -> bool {
+        self.shared(other) >= min_overlap * min_overlap.saturating_sub(1) / 2
+    }
+
+    // candidate (self index, other index) beacon pairs, most likely first
+    fn correspondences(&self, other: &Fingerprint, min_overlap: usize) -> Vec<(usize, usize)> {
+        let mut votes: HashMap<(usize, usize), usize> = HashMap::new();
+        for (d, pairs) in &self.distances {
+            if let Some(others) = other.distances.get(d) {
+                for &(p, q) in pairs {
+                    for &(r, s) in others {
+                        for candidate in [(p, r), (p, s), (q, r), (q, s)] {
+                            *votes.entry(candidate).or_default() += 1;
+                        }
                     }
                 }
             }
         }
+        // a beacon in the overlap is at a matching distance from every other beacon in the overlap
+        votes
+            .into_iter()
+            .filter(|(_, count)| *count + 1 >= min_overlap)
+            .sorted_by_key(|&(candidate, count)| (Reverse(count), candidate))
+            .map(|(candidate, _)| candidate)
+            .collect()
     }
-    ans
 }
 
-// pub fn dfs(i: usize, j: usize, visited: &mut HashSet<(usize, usize)>, scanners: &Vec<Vec<Point>>, alignments: &mut HashSet<usize, Alignment>) {
-//     if visited.contains(&(i, j)) {
-//         return;
-//     }
-//
-//     if let Some(alignment_i_j) = Alignment::from(&scanners[j], &scanners[i]) {
-//         println!("Found alignment between {} {}, {:?}", i, j, alignment_i_j.translation);
-//         for point in &scanners[j] {
-//             // find the coordinate of this point in the base orientation
-//             let point0 = alignment_i_j.apply(*point);
-//
-//             // println!("{},{},{}", point0.0[0], point0.0[1], point0.0[2]);
-//             all_points.insert(point0);
-//         }
-//     }
-//
-// }
-
-fn solve(input: &str) -> (HashSet<Point>, HashMap<usize, Alignment>) {
+fn parse(input: &str) -> Vec<Vec<Point>> {
     let mut scanners: Vec<Vec<Point>> = Vec::new();
     let mut current: Vec<Point> = Vec::new();
     for line in input.lines() {
@@ -240,63 +246,160 @@ fn solve(input: &str) -> (HashSet<Point>, HashMap<usize, Alignment>) {
         }
     }
     scanners.push(current);
+    scanners
+}
+
+// Scanners that could be aligned with each other, with poses and beacons relative to the first one.
+#[derive(Debug)]
+pub struct Component {
+    pub scanners: Vec<usize>,
+    pub poses: HashMap<usize, Pose>,
+    pub beacons: HashSet<Point>,
+}
+
+impl Component {
+    fn beacons_sorted(&self) -> Vec<Point> {
+        self.beacons.iter().copied().sorted().collect()
+    }
+
+    fn scanner_positions(&self) -> Vec<Point> {
+        self.scanners.iter().map(|scanner| self.poses[scanner].position).collect()
+    }
+
+    // ASCII PLY point cloud; the `scanner` property is 1 for scanners and 0 for beacons,
+    // which are also coloured red and white respectively
+    pub fn to_ply(&self) -> String {
+        let beacons = self.beacons_sorted();
+        let scanners = self.scanner_positions();
+        let mut out = String::new();
+        writeln!(out, "ply").unwrap();
+        writeln!(out, "format ascii 1.0").unwrap();
+        writeln!(out, "element vertex {}", beacons.len() + scanners.len()).unwrap();
+        for property in ["int x", "int y", "int z", "uchar red", "uchar green", "uchar blue", "uchar scanner"] {
+            writeln!(out, "property {}", property).unwrap();
+        }
+        writeln!(out, "end_header").unwrap();
+        for Point([x, y, z]) in beacons {
+            writeln!(out, "{} {} {} 255 255 255 0", x, y, z).unwrap();
+        }
+        for Point([x, y, z]) in scanners {
+            writeln!(out, "{} {} {} 255 0 0 1", x, y, z).unwrap();
+        }
+        out
+    }
 
-    let mut all_points = HashSet::new();
-    for point in &scanners[0] {
-        all_points.insert(*point);
+    // OBJ vertices, with beacons and scanners as separate objects
+    pub fn to_obj(&self) -> String {
+        let mut out = String::new();
+        for (name, points) in [("beacons", self.beacons_sorted()), ("scanners", self.scanner_positions())] {
+            writeln!(out, "o {}", name).unwrap();
+            for Point([x, y, z]) in points {
+                writeln!(out, "v {} {} {}", x, y, z).unwrap();
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug)]
+pub struct Survey {
+    pub components: Vec<Component>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DisconnectedError {
+    pub groups: Vec<Vec<usize>>,
+}
+
+impl Survey {
+    pub fn is_connected(&self) -> bool {
+        self.components.len() <= 1
     }
 
-    let mut alignments: HashMap<usize, Alignment> = HashMap::new();
-    alignments.insert(0, Alignment::identity());
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        self.components.iter().map(|component| component.scanners.clone()).collect()
+    }
 
+    pub fn into_connected(mut self) -> Result<Component, DisconnectedError> {
+        if self.is_connected() {
+            Ok(self.components.remove(0))
+        } else {
+            Err(DisconnectedError { groups: self.groups() })
+        }
+    }
+}
+
+fn align_component(root: usize, scanners: &[Vec<Point>], fingerprints: &[Fingerprint], min_overlap: usize, aligned: &mut [bool]) -> Component {
+    let mut all_points: HashSet<Point> = scanners[root].iter().copied().collect();
+
+    let mut poses: HashMap<usize, Pose> = HashMap::new();
+    poses.insert(root, Pose::identity());
+    aligned[root] = true;
+
+    let mut order = vec![root];
     let mut queue = VecDeque::new();
-    queue.push_back(0);
+    queue.push_back(root);
 
     while let Some(i) = queue.pop_front() {
         for j in 0..scanners.len() {
-            if j == i || alignments.contains_key(&j) {
+            if aligned[j] || !fingerprints[j].may_overlap(&fingerprints[i], min_overlap) {
                 continue;
             }
 
-            if let Some(alignment_i_j) = Alignment::from(&scanners[j], &scanners[i]) {
-                println!("Found alignment between {} {}, {:?}", i, j, alignment_i_j.translation);
-                let mut oriented_scanners = Vec::new();
+            let relative = Pose::from_fingerprints(&scanners[j], &fingerprints[j], &scanners[i], &fingerprints[i], min_overlap);
+            if let Some(pose_i_j) = relative {
+                let pose_root_j = poses[&i].compose(&pose_i_j);
                 for point in &scanners[j] {
-                    // find the coordinate of this point in the base orientation
-                    let point0 = alignment_i_j.apply(*point);
-                    // println!("{},{},{}", point0.0[0], point0.0[1], point0.0[2]);
-                    all_points.insert(point0);
-                    oriented_scanners.push(point0);
+                    // find the coordinate of this point in the root's orientation
+                    all_points.insert(pose_root_j.apply(*point));
                 }
 
-                scanners[j] = oriented_scanners;
+                aligned[j] = true;
+                order.push(j);
                 queue.push_back(j);
-                alignments.insert(j, alignment_i_j);
+                poses.insert(j, pose_root_j);
             }
         }
     }
-    (all_points, alignments)
+    order.sort_unstable();
+    Component { scanners: order, poses, beacons: all_points }
+}
+
+// Every connected group of scanners, ordered by their lowest scanner; the first one contains scanner 0.
+pub fn survey(input: &str, min_overlap: usize) -> Survey {
+    let scanners = parse(input);
+    let fingerprints = scanners.iter().map(|scanner| Fingerprint::of(scanner)).collect_vec();
+
+    let mut aligned = vec![false; scanners.len()];
+    let mut components = Vec::new();
+    while let Some(root) = aligned.iter().position(|done| !done) {
+        components.push(align_component(root, &scanners, &fingerprints, min_overlap, &mut aligned));
+    }
+    Survey { components }
+}
+
+// The full map relative to scanner 0, or the groups found if some scanners never overlap.
+pub fn solve(input: &str, min_overlap: usize) -> Result<Component, DisconnectedError> {
+    survey(input, min_overlap).into_connected()
 }
 
 pub fn part1(input: &str) -> i64 {
-    let (points, _) = solve(input);
-    points.len() as i64
+    let map = solve(input, MIN_OVERLAP).unwrap();
+    map.beacons.len() as i64
 }
 
 pub fn part2(input: &str) -> i64 {
-    let (_, alignments) = solve(input);
-    alignments
+    let map = solve(input, MIN_OVERLAP).unwrap();
+    map.poses
         .values()
         .combinations(2)
         .map(|comb| {
             let a = comb[0];
             let b = comb[1];
-            let dist = a.translation.manhattan_dist(&b.translation);
-            println!("{:?} {:?} {}", a.translation, b.translation, dist);
-            dist
+            a.position.manhattan_dist(&b.position)
         })
         .max()
-        .unwrap() as i64
+        .unwrap_or(0) as i64
 }
 
 
@@ -313,7 +416,7 @@ mod tests {
 
     #[test]
     fn matrix_mul() {
-        let matrix = Matrix([
+        let matrix = Rotation([
             [1, 0, 0],
             [0, 1, 0],
             [0, 0, 1],
@@ -321,6 +424,13 @@ mod tests {
         let point = Point([1, 2, 3]);
 
         assert_eq!(matrix * point, point);
+
+        let matrix = Rotation([
+            [0, -1, 0],
+            [1, 0, 0],
+            [0, 0, 1],
+        ]);
+        assert_eq!(matrix * point, Point([-2, 1, 3]));
     }
 
     #[test]
@@ -343,33 +453,43 @@ mod tests {
 
     #[test]
     fn test_unit_vector_orientation() {
-        let matrix = Matrix([
-            [1, 0, 0],
-            [0, 1, 0],
-            [0, 0, 1],
-        ]);
-        assert!(test_orientation(matrix))
+        assert!(Rotation::identity().is_proper())
     }
 
     #[test]
     fn test_all_orientations() {
-        assert_eq!(all_orientations().len(), 24);
+        assert_eq!(Rotation::all().len(), 24);
     }
 
+    #[test]
     fn test_all_orientations2() {
         let p = Point([1, 2, 3]);
-        let mut a = HashSet::new();
-        let mut b = HashSet::new();
-        for orientation in all_orientations() {
-            a.insert(orientation * p);
-        }
+        let a: HashSet<_> = Rotation::all().into_iter().map(|rotation| rotation * p).collect();
+        assert_eq!(a.len(), 24);
+    }
 
-        for o in &ORIENTATIONS {
-            b.insert(orient(p, o));
+    #[test]
+    fn test_rotation_composition() {
+        let rotations = Rotation::all();
+        let p = Point([1, 2, 3]);
+        for a in &rotations {
+            assert_eq!(*a * a.inverse(), Rotation::identity());
+            for b in &rotations {
+                let composed = *a * *b;
+                assert!(rotations.contains(&composed));
+                assert_eq!(composed * p, *a * (*b * p));
+            }
         }
+    }
 
-        assert_eq!(a.len(), b.len());
-        assert_eq!(a, b);
+    #[test]
+    fn test_pose_inverse() {
+        let rotations = Rotation::all();
+        let outer = Pose { rotation: rotations[5], position: Point([68, -1246, -43]) };
+        let inner = Pose { rotation: rotations[17], position: Point([-20, 3, 1000]) };
+        let p = Point([1, 2, 3]);
+        assert_eq!(outer.compose(&inner).apply(p), outer.apply(inner.apply(p)));
+        assert_eq!(outer.compose(&outer.inverse()), Pose::identity());
     }
 
     #[test]
@@ -402,11 +522,14 @@ mod tests {
             Point([-391, 539, -444]),
             Point([553, 889, -390]),
         ];
-        let alignment = Alignment::from(&b, &a).unwrap();
+        let alignment = Pose::from_fingerprints(&b, &Fingerprint::of(&b), &a, &Fingerprint::of(&a), MIN_OVERLAP).unwrap();
 
         // assert_eq!(alignment.translation, Point([68, -1246, -43]));
         let c = b.iter().map(|point| alignment.apply(*point)).collect_vec();
         assert_eq!(a, c);
+
+        assert!(Fingerprint::of(&b).may_overlap(&Fingerprint::of(&a), MIN_OVERLAP));
+        assert!(Pose::from_fingerprints(&b, &Fingerprint::of(&b), &a, &Fingerprint::of(&a), MIN_OVERLAP + 1).is_none());
     }
 
     #[test]
@@ -769,4 +892,60 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(EXAMPLE), 3621)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scanner_poses_example() {
+        let poses = solve(EXAMPLE, MIN_OVERLAP).unwrap().poses;
+        assert_eq!(poses[&0], Pose::identity());
+        assert_eq!(poses[&1].position, Point([68, -1246, -43]));
+        assert_eq!(poses[&2].position, Point([1105, -1205, 1229]));
+        assert_eq!(poses[&3].position, Point([-92, -2380, -20]));
+        assert_eq!(poses[&4].position, Point([-20, -1133, 1061]));
+
+        assert_eq!(poses[&1].apply(Point([686, 422, 578])), Point([-618, -824, -621]));
+
+        // scanner 4 was found through scanner 1, so its rotation must be composed
+        let scanners = parse(EXAMPLE);
+        let seen_by_1: HashSet<_> = scanners[1].iter().map(|p| poses[&1].apply(*p)).collect();
+        let seen_by_4: HashSet<_> = scanners[4].iter().map(|p| poses[&4].apply(*p)).collect();
+        assert_eq!(seen_by_1.intersection(&seen_by_4).count(), 12);
+    }
+
+    #[test]
+    fn disconnected_scanners() {
+        let input = format!("{}\n\n--- scanner 5 ---\n1,2,3\n4,5,6\n7,8,9", EXAMPLE);
+        let survey = survey(&input, MIN_OVERLAP);
+        assert!(!survey.is_connected());
+        assert_eq!(survey.groups(), vec![vec![0, 1, 2, 3, 4], vec![5]]);
+        assert_eq!(survey.components[0].beacons.len(), 79);
+        assert_eq!(survey.components[1].beacons.len(), 3);
+
+        assert_eq!(
+            solve(&input, MIN_OVERLAP).unwrap_err(),
+            DisconnectedError { groups: vec![vec![0, 1, 2, 3, 4], vec![5]] }
+        );
+    }
+
+    #[test]
+    fn export_point_cloud() {
+        let scanners_0_1 = EXAMPLE.split("\n\n--- scanner 2 ---").next().unwrap();
+        let map = solve(scanners_0_1, MIN_OVERLAP).unwrap();
+        assert_eq!(map.beacons.len(), 38);
+
+        let ply = map.to_ply();
+        let lines = ply.lines().collect_vec();
+        assert_eq!(lines[2], "element vertex 40");
+        assert_eq!(lines.iter().position(|line| *line == "end_header"), Some(10));
+        assert_eq!(lines.len(), 11 + 40);
+        assert_eq!(lines[11], "-892 524 684 255 255 255 0");
+        assert_eq!(lines[49], "0 0 0 255 0 0 1");
+        assert_eq!(lines[50], "68 -1246 -43 255 0 0 1");
+
+        let obj = map.to_obj();
+        let lines = obj.lines().collect_vec();
+        assert_eq!(lines[0], "o beacons");
+        assert_eq!(lines[1], "v -892 524 684");
+        assert_eq!(lines[39], "o scanners");
+        assert_eq!(&lines[40..], ["v 0 0 0", "v 68 -1246 -43"]);
+    }
+}
@@ -1,18 +1,20 @@
 use std::str::FromStr;
 
-enum Command {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
   Down(i32),
   Up(i32),
   Forward(i32),
 }
 
-struct DirectionParseError;
+#[derive(Debug, PartialEq, Eq)]
+pub struct DirectionParseError;
 
 impl FromStr for Command {
   type Err = DirectionParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
-    let tokens: Vec<&str> = s.split(' ').collect();
+    let tokens: Vec<&str> = s.split_whitespace().collect();
     if tokens.len() != 2 {
       Err(DirectionParseError)
     } else {
@@ -33,36 +35,53 @@ impl FromStr for Command {
   }
 }
 
-#[derive(Default)]
-struct Submarine {
-  horizontal: i32,
-  depth: i32,
-  aim: i32,
+// A line that isn't a command, 0-based.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LineError {
+  pub line: usize,
+  pub text: String,
 }
 
-pub fn part1(input: &[String]) -> i32 {
-  fn transform1(sub: &mut Submarine, command: &Command) {
+// Blank lines are skipped; anything else has to be a command.
+pub fn parse(input: &[String]) -> Result<Vec<Command>, LineError> {
+  input
+    .iter()
+    .enumerate()
+    .filter(|(_, s)| !s.trim().is_empty())
+    .map(|(line, s)| Command::from_str(s).map_err(|_| LineError { line, text: s.clone() }))
+    .collect()
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Submarine {
+  pub horizontal: i32,
+  pub depth: i32,
+  pub aim: i32,
+}
+
+// How a command moves the submarine. Closures taking (&mut Submarine, &Command) are models too.
+pub trait Model {
+  fn apply(&self, sub: &mut Submarine, command: &Command);
+}
+
+// up and down change the depth directly
+pub struct Simple;
+
+impl Model for Simple {
+  fn apply(&self, sub: &mut Submarine, command: &Command) {
     match command {
       Command::Down(x) => sub.depth += x,
       Command::Up(x) => sub.depth -= x,
       Command::Forward(x) => sub.horizontal += x,
     }
   }
-
-  let commands = input
-    .iter()
-    .filter_map(|s| Command::from_str(s).ok());
-
-  let mut sub = Submarine::default();
-  for command in commands {
-    transform1(&mut sub, &command);
-  }
-
-  sub.horizontal * sub.depth
 }
 
-pub fn part2(input: &[String]) -> i32 {
-  fn transform(sub: &mut Submarine, command: &Command) {
+// up and down change the aim, forward dives along it
+pub struct Aimed;
+
+impl Model for Aimed {
+  fn apply(&self, sub: &mut Submarine, command: &Command) {
     match command {
       Command::Down(x) => sub.aim += x,
       Command::Up(x) => sub.aim -= x,
@@ -72,15 +91,94 @@ pub fn part2(input: &[String]) -> i32 {
       },
     }
   }
+}
+
+impl<F: Fn(&mut Submarine, &Command)> Model for F {
+  fn apply(&self, sub: &mut Submarine, command: &Command) {
+    self(sub, command)
+  }
+}
+
+impl Submarine {
+  pub fn execute(&mut self, model: &impl Model, command: &Command) {
+    model.apply(self, command);
+  }
 
-  let commands = input
-    .iter()
-    .filter_map(|s| Command::from_str(s).ok());
+  pub fn run(model: &impl Model, commands: &[Command]) -> Submarine {
+    let mut sub = Submarine::default();
+    for command in commands {
+      sub.execute(model, command);
+    }
+    sub
+  }
 
-  let mut sub = Submarine::default();
-  for command in commands {
-    transform(&mut sub, &command);
+  // The starting state followed by the state after each command.
+  pub fn trace(model: &impl Model, commands: &[Command]) -> Vec<Submarine> {
+    let mut sub = Submarine::default();
+    let mut trace = vec![sub];
+    for command in commands {
+      sub.execute(model, command);
+      trace.push(sub);
+    }
+    trace
   }
+}
 
+fn solve(input: &[String], model: &impl Model) -> i32 {
+  let commands = parse(input).expect("invalid command");
+  let sub = Submarine::run(model, &commands);
   sub.horizontal * sub.depth
-}
\ No newline at end of file
+}
+
+pub fn part1(input: &[String]) -> i32 {
+  solve(input, &Simple)
+}
+
+pub fn part2(input: &[String]) -> i32 {
+  solve(input, &Aimed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn example() -> Vec<String> {
+    ["forward 5", "down 5", "forward 8", "up 3", "down 8", "forward 2"]
+      .iter()
+      .map(|s| s.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn example1() {
+    assert_eq!(part1(&example()), 150);
+  }
+
+  #[test]
+  fn example2() {
+    assert_eq!(part2(&example()), 900);
+  }
+
+  #[test]
+  fn bad_lines_are_reported() {
+    let mut input = example();
+    input.insert(2, "sideways 4".to_string());
+    input.push(String::new());
+    assert_eq!(parse(&input), Err(LineError { line: 2, text: "sideways 4".to_string() }));
+  }
+
+  #[test]
+  fn trace_and_custom_model() {
+    let commands = parse(&example()).unwrap();
+    let depths: Vec<i32> = Submarine::trace(&Aimed, &commands).iter().map(|sub| sub.depth).collect();
+    assert_eq!(depths, [0, 0, 0, 40, 40, 40, 60]);
+
+    // a submarine whose up and down commands are swapped
+    let inverted = |sub: &mut Submarine, command: &Command| match *command {
+      Command::Down(x) => Simple.apply(sub, &Command::Up(x)),
+      Command::Up(x) => Simple.apply(sub, &Command::Down(x)),
+      forward => Simple.apply(sub, &forward),
+    };
+    assert_eq!(Submarine::run(&inverted, &commands), Submarine { horizontal: 15, depth: -10, aim: 0 });
+  }
+}
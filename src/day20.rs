@@ -1,87 +1,262 @@
-use std::collections::HashSet;
+use std::iter;
+use std::str::FromStr;
 
-pub fn solve(input: &str, simulation_time: usize) -> u64 {
-    let mut lines = input.lines();
+use itertools::Itertools;
 
-    let enhancement = lines.next().unwrap();
-    let enhancement: Vec<char> = enhancement.chars().collect();
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingEnhancement,
+    EnhancementLength(usize),
+    // 0-based position in the input
+    InvalidPixel { line: usize, column: usize, found: char },
+}
 
-    let _ = lines.next().unwrap();
+fn parse_pixel(c: char, line: usize, column: usize) -> Result<bool, ParseError> {
+    match c {
+        '#' => Ok(true),
+        '.' => Ok(false),
+        found => Err(ParseError::InvalidPixel { line, column, found }),
+    }
+}
 
-    let mut image: HashSet<(i64, i64)> = HashSet::new();
+// The 512-entry rule that maps a 3x3 neighbourhood to the new pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Enhancement([bool; 512]);
 
-    let mut min_x: i64 = 0;
-    let mut min_y: i64 = 0;
-    let mut max_x: i64 = 0;
-    let mut max_y: i64 = 0;
+impl FromStr for Enhancement {
+    type Err = ParseError;
 
-    for (y, line) in lines.enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            if c == '#' {
-                image.insert((x as i64, y as i64));
-            }
-            max_x = max_x.max(x as i64);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let length = s.chars().count();
+        if length != 512 {
+            return Err(ParseError::EnhancementLength(length));
+        }
+        let mut rule = [false; 512];
+        for (column, c) in s.chars().enumerate() {
+            rule[column] = parse_pixel(c, 0, column)?;
         }
-        max_y = max_y.max(y as i64);
-    }
-
-    let mut background = '.';
-    for _ in 0..simulation_time {
-        let mut new_image : HashSet<(i64, i64)> = HashSet::new();
-        for y in min_y-2..=max_y+2 {
-            for x in min_x-2..=max_x+2 {
-                let offset = sum_grid(x, y, &image, (min_x, min_y, max_x, max_y), background);
-                let pixel = enhancement[offset];
-                if pixel == '#' {
-                    new_image.insert((x, y));
+        Ok(Enhancement(rule))
+    }
+}
+
+impl Enhancement {
+    fn get(&self, offset: usize) -> bool {
+        self.0[offset]
+    }
+
+    pub fn next_background(&self, background: bool) -> bool {
+        if background { self.get(511) } else { self.get(0) }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LitCount {
+    Finite(u64),
+    // the background itself is lit
+    Infinite,
+}
+
+// Lit pixels packed 64 to a word, row by row. Everything outside the
+// width x height window has the `background` colour, which can flip each step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    rows: Vec<u64>,
+    background: bool,
+}
+
+impl Image {
+    fn new(width: usize, height: usize, background: bool) -> Self {
+        let words_per_row = width.div_ceil(64);
+        Image { width, height, words_per_row, rows: vec![0; words_per_row * height], background }
+    }
+
+    // `first_line` is only used to report where a bad pixel is
+    fn parse<'a>(lines: impl Iterator<Item = &'a str>, first_line: usize) -> Result<Self, ParseError> {
+        let lines: Vec<(usize, &str)> = lines
+            .enumerate()
+            .map(|(i, line)| (first_line + i, line))
+            .filter(|(_, line)| !line.is_empty())
+            .collect();
+        let width = lines.iter().map(|(_, line)| line.chars().count()).max().unwrap_or(0);
+        let mut image = Image::new(width, lines.len(), false);
+        for (y, (line_number, line)) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if parse_pixel(c, *line_number, x)? {
+                    image.set(x, y);
                 }
             }
         }
+        Ok(image)
+    }
 
-        // everything that is from boundary outward will be changed
-        if background == '.' {
-            background = enhancement[0];
-        } else {
-            background = enhancement[511];
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    fn set(&mut self, x: usize, y: usize) {
+        self.rows[y * self.words_per_row + x / 64] |= 1 << (x % 64);
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return self.background;
         }
-        image = new_image;
-        // adjusting boundary
-        min_x -= 2;
-        min_y -= 2;
-        max_x += 2;
-        max_y += 2;
+        let (x, y) = (x as usize, y as usize);
+        self.rows[y * self.words_per_row + x / 64] >> (x % 64) & 1 == 1
     }
 
-    image.len() as u64
-}
+    fn row(&self, y: i64) -> Option<&[u64]> {
+        if y < 0 || y >= self.height as i64 {
+            return None;
+        }
+        let start = y as usize * self.words_per_row;
+        Some(&self.rows[start..start + self.words_per_row])
+    }
 
-fn sum_grid(x0: i64, y0: i64, image: &HashSet<(i64, i64)>, boundary: (i64, i64, i64, i64), background: char) -> usize {
-    let mut grid_sum = 0;
-    let (min_x, min_y, max_x, max_y) = boundary;
-    for y in y0-1..=y0+1 {
-        for x in x0-1..=x0+1 {
-            grid_sum *= 2;
-            let mut pixel = background;
-            if min_x <= x && x <= max_x && min_y <= y && y <= max_y {
-                if image.contains(&(x, y)) {
-                    pixel = '#';
-                } else {
-                    pixel = '.';
-                }
+    // bit x of a row, with the background beyond either end (and for missing rows)
+    fn row_bit(&self, row: Option<&[u64]>, x: i64) -> usize {
+        match row {
+            Some(words) if x >= 0 && x < self.width as i64 => (words[x as usize / 64] >> (x % 64) & 1) as usize,
+            _ => self.background as usize,
+        }
+    }
+
+    // Only pixels within one of the current window can see anything but the
+    // background, so the image grows by one on each side.
+    pub fn enhance(&self, enhancement: &Enhancement) -> Image {
+        let background = enhancement.next_background(self.background);
+        let mut image = Image::new(self.width + 2, self.height + 2, background);
+        for y in 0..image.height {
+            // output (x, y) is centred on input (x - 1, y - 1)
+            let source_y = y as i64 - 1;
+            let rows = [self.row(source_y - 1), self.row(source_y), self.row(source_y + 1)];
+            // 3-bit window per row, slid one column at a time
+            let mut windows = [0usize; 3];
+            for (window, row) in windows.iter_mut().zip(rows) {
+                *window = self.row_bit(row, -2) << 1 | self.row_bit(row, -1);
             }
-            if pixel == '#' {
-                grid_sum += 1;
+            for x in 0..image.width {
+                for (window, row) in windows.iter_mut().zip(rows) {
+                    *window = (*window << 1 | self.row_bit(row, x as i64)) & 0b111;
+                }
+                let offset = windows[0] << 6 | windows[1] << 3 | windows[2];
+                if enhancement.get(offset) {
+                    image.set(x, y);
+                }
             }
         }
+        image
+    }
+
+    pub fn lit_count(&self) -> LitCount {
+        if self.background {
+            LitCount::Infinite
+        } else {
+            LitCount::Finite(self.rows.iter().map(|word| word.count_ones() as u64).sum())
+        }
+    }
+
+    // (lit, inside the window) for the window plus `margin` pixels of background all round
+    fn pixel_rows(&self, margin: usize) -> impl Iterator<Item = Vec<(bool, bool)>> + '_ {
+        let margin = margin as i64;
+        let (width, height) = (self.width as i64, self.height as i64);
+        (-margin..height + margin).map(move |y| {
+            (-margin..width + margin)
+                .map(|x| (self.get(x, y), 0 <= x && x < width && 0 <= y && y < height))
+                .collect()
+        })
+    }
+
+    pub fn to_ascii(&self, margin: usize) -> String {
+        self.pixel_rows(margin)
+            .map(|row| row.iter().map(|&(lit, _)| if lit { '#' } else { '.' }).collect::<String>() + "\n")
+            .collect()
+    }
+
+    // plain (ASCII) PBM, 1 is lit
+    pub fn to_pbm(&self, margin: usize) -> String {
+        let size = format!("{} {}", self.width + 2 * margin, self.height + 2 * margin);
+        let body: String = self.pixel_rows(margin)
+            .map(|row| row.iter().map(|&(lit, _)| if lit { "1" } else { "0" }).join(" ") + "\n")
+            .collect();
+        format!("P1\n{}\n{}", size, body)
+    }
+
+    // plain PGM where the background outside the window is drawn at half intensity,
+    // so a flickering background stands out from the image itself
+    pub fn to_pgm(&self, margin: usize) -> String {
+        let size = format!("{} {}", self.width + 2 * margin, self.height + 2 * margin);
+        let body: String = self.pixel_rows(margin)
+            .map(|row| {
+                row.iter()
+                    .map(|&pixel| match pixel {
+                        (true, true) => 4,
+                        (true, false) => 3,
+                        (false, false) => 1,
+                        (false, true) => 0,
+                    })
+                    .join(" ") + "\n"
+            })
+            .collect();
+        format!("P2\n{}\n4\n{}", size, body)
     }
-    grid_sum
 }
+
+pub fn parse(input: &str) -> Result<(Enhancement, Image), ParseError> {
+    let mut lines = input.lines();
+    let enhancement: Enhancement = lines.next().ok_or(ParseError::MissingEnhancement)?.parse()?;
+    let image = Image::parse(lines, 1)?;
+    Ok((enhancement, image))
+}
+
+// The input image followed by the result of every enhancement step, forever.
+pub fn enhancements(input: &str) -> Result<impl Iterator<Item = Image>, ParseError> {
+    let (enhancement, image) = parse(input)?;
+    Ok(iter::successors(Some(image), move |image| Some(image.enhance(&enhancement))))
+}
+
+// ASCII frames padded to the size of the largest one, each with a header line.
+pub fn flipbook(frames: &[Image]) -> String {
+    let width = frames.iter().map(|frame| frame.width()).max().unwrap_or(0);
+    frames
+        .iter()
+        .enumerate()
+        .map(|(step, frame)| {
+            let background = if frame.background() { '#' } else { '.' };
+            let margin = (width - frame.width()) / 2 + 1;
+            format!("step {} (background {})\n{}", step, background, frame.to_ascii(margin))
+        })
+        .join("\n")
+}
+
+pub fn solve(input: &str, simulation_time: usize) -> Result<LitCount, ParseError> {
+    Ok(enhancements(input)?.nth(simulation_time).unwrap().lit_count())
+}
+
+fn finite_solve(input: &str, simulation_time: usize) -> u64 {
+    match solve(input, simulation_time).unwrap() {
+        LitCount::Finite(count) => count,
+        LitCount::Infinite => panic!("infinitely many pixels are lit after {} steps", simulation_time),
+    }
+}
+
 pub fn part1(input: &str) -> u64 {
-    solve(input, 2)
+    finite_solve(input, 2)
 }
 
 pub fn part2(input: &str) -> u64 {
-    solve(input, 50)
+    finite_solve(input, 50)
 }
 
 
@@ -105,6 +280,82 @@ mod tests {
         )
     }
 
+    #[test]
+    fn example_50_steps() {
+        assert_eq!(
+            super::solve(
+                include_str!("input/day20_example.txt"), 50
+            ), Ok(super::LitCount::Finite(3351))
+        )
+    }
+
+    #[test]
+    fn image_grows_by_one_per_step() {
+        let (enhancement, image) = super::parse(include_str!("input/day20_example.txt")).unwrap();
+        assert_eq!((image.width(), image.height()), (5, 5));
+        let image = image.enhance(&enhancement).enhance(&enhancement);
+        assert_eq!((image.width(), image.height()), (9, 9));
+        assert!(!image.background());
+        assert!(image.get(2, 2));
+        assert!(!image.get(-100, 100));
+    }
+
+    #[test]
+    fn render_example() {
+        let image = super::enhancements(include_str!("input/day20_example.txt")).unwrap().next().unwrap();
+        assert_eq!(image.to_ascii(0), "#..#.\n#....\n##..#\n..#..\n..###\n");
+        assert!(image.to_ascii(1).starts_with(".......\n.#..#..\n"));
+        assert!(image.to_pbm(0).starts_with("P1\n5 5\n1 0 0 1 0\n"));
+        assert!(image.to_pgm(1).starts_with("P2\n7 7\n4\n1 1 1 1 1 1 1\n1 4 0 0 4 0 1\n"));
+    }
+
+    #[test]
+    fn flickering_background() {
+        // the real input turns every dark 3x3 square lit, and every lit one dark
+        let frames: Vec<_> = super::enhancements(include_str!("input/day20.txt")).unwrap().take(3).collect();
+        assert_eq!(frames.iter().map(|frame| frame.background()).collect::<Vec<_>>(), [false, true, false]);
+
+        let flipbook = super::flipbook(&frames);
+        let headers: Vec<_> = flipbook.lines().filter(|line| line.starts_with("step")).collect();
+        assert_eq!(headers, ["step 0 (background .)", "step 1 (background #)", "step 2 (background .)"]);
+        assert!(flipbook.lines().nth(1).unwrap().chars().all(|c| c == '.'));
+        let step1 = flipbook.lines().position(|line| line == "step 1 (background #)").unwrap();
+        assert!(flipbook.lines().nth(step1 + 1).unwrap().chars().all(|c| c == '#'));
+    }
+
+    #[test]
+    fn malformed_enhancement() {
+        use super::ParseError;
+
+        let example = include_str!("input/day20_example.txt");
+        assert_eq!(super::parse(""), Err(ParseError::MissingEnhancement));
+        assert_eq!(super::parse(&example[1..]), Err(ParseError::EnhancementLength(511)));
+        assert_eq!(
+            super::parse(&example.replacen('#', "x", 1)),
+            Err(ParseError::InvalidPixel { line: 0, column: 2, found: 'x' })
+        );
+        assert_eq!(
+            super::parse(&example.replace("\n#..#.\n", "\n#..o.\n")),
+            Err(ParseError::InvalidPixel { line: 2, column: 3, found: 'o' })
+        );
+    }
+
+    #[test]
+    fn infinite_lit_pixels() {
+        use super::LitCount;
+
+        // the real input flickers: the background is lit after every odd step
+        let input = include_str!("input/day20.txt");
+        assert_eq!(super::solve(input, 1), Ok(LitCount::Infinite));
+        assert_eq!(super::solve(input, 2), Ok(LitCount::Finite(5498)));
+
+        // an all-lit rule never lets the background go dark again
+        let input = format!("{}\n\n#.\n.#", "#".repeat(512));
+        assert_eq!(super::solve(&input, 0), Ok(LitCount::Finite(2)));
+        assert_eq!(super::solve(&input, 1), Ok(LitCount::Infinite));
+        assert_eq!(super::solve(&input, 2), Ok(LitCount::Infinite));
+    }
+
     #[test]
     fn example2() {
         assert_eq!(
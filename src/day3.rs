@@ -1,81 +1,157 @@
-pub fn part1(input: &[String]) -> usize {
-    let sample_count = input.len();
-    let majority_threshold = sample_count / 2;
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReportError {
+    TooWide(usize),
+    // 0-based positions in the input
+    RaggedLine { line: usize, expected: usize, found: usize },
+    InvalidBit { line: usize, column: usize, found: char },
+}
 
-    if sample_count <= 0 {
-        return 0;
+// Which value wins a column when there are as many zeros as ones.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    Zero,
+    One,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Criterion {
+    MostCommon(TieBreak),
+    LeastCommon(TieBreak),
+}
+
+impl Criterion {
+    fn keep_ones(&self, zeros: usize, ones: usize) -> bool {
+        match *self {
+            Criterion::MostCommon(tie) => ones > zeros || (ones == zeros && tie == TieBreak::One),
+            Criterion::LeastCommon(tie) => ones < zeros || (ones == zeros && tie == TieBreak::One),
+        }
     }
+}
+
+// Each number as a u64, plus one bitset per column (bit i of a column set is
+// number i's bit) so ones can be counted with popcounts.
+pub struct Report {
+    width: usize,
+    numbers: Vec<u64>,
+    columns: Vec<Vec<u64>>,
+}
 
-    let bit_count = input[0].len();
-    let mut one_freq: Vec<usize> = vec![0; bit_count];
+impl Report {
+    // The width is taken from the first line.
+    pub fn parse(input: &[String]) -> Result<Report, ReportError> {
+        let width = input.iter().map(|line| line.trim()).find(|line| !line.is_empty()).map_or(0, |line| line.chars().count());
+        Report::parse_with_width(input, width)
+    }
+
+    pub fn parse_with_width(input: &[String], width: usize) -> Result<Report, ReportError> {
+        if width > 64 {
+            return Err(ReportError::TooWide(width));
+        }
+        let mut numbers = Vec::new();
+        for (line, s) in input.iter().enumerate() {
+            let s = s.trim();
+            if s.is_empty() {
+                continue;
+            }
+            if s.chars().count() != width {
+                return Err(ReportError::RaggedLine { line, expected: width, found: s.chars().count() });
+            }
+            let mut number = 0;
+            for (column, bit) in s.chars().enumerate() {
+                number = number << 1 | match bit {
+                    '0' => 0,
+                    '1' => 1,
+                    found => return Err(ReportError::InvalidBit { line, column, found }),
+                };
+            }
+            numbers.push(number);
+        }
 
-    for line in input {
-        for (index, bit) in line.chars().enumerate() {
-            if bit == '1' {
-                one_freq[index] += 1;
+        // column 0 is the leftmost, most significant bit
+        let mut columns = vec![vec![0; numbers.len().div_ceil(64)]; width];
+        for (i, number) in numbers.iter().enumerate() {
+            for (column, bits) in columns.iter_mut().enumerate() {
+                bits[i / 64] |= (number >> (width - 1 - column) & 1) << (i % 64);
             }
         }
+        Ok(Report { width, numbers, columns })
     }
 
-    //epsilon = most commmon
-    //gamme = least common
-    let mut epsilon: usize = 0;
-    let mut gamma: usize = 0;
-    for freq in one_freq {
-        epsilon *= 2;
-        gamma *= 2;
-        if freq > majority_threshold {
-            epsilon += 1;
-        } else {
-            gamma += 1;
-        }
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    epsilon * gamma
-}
+    pub fn numbers(&self) -> &[u64] {
+        &self.numbers
+    }
 
-fn choosing<'a, F>(input: &'a [String], bit_criteria: F) -> usize
-where
-    F: Fn(Vec<&'a str>, Vec<&'a str>) -> Vec<&'a str>,
-{
-    let mut input: Vec<&str> = input.iter().map(|x| x.as_str()).collect();
-    let mut index: usize = 0;
-    let mut counts: (Vec<&str>, Vec<&str>) = input
-        .iter()
-        .partition(|x| x.chars().nth(index) == Some('0'));
-    while counts.0.len() + counts.1.len() > 1 {
-        input = bit_criteria(counts.0, counts.1);
-        index += 1;
-        counts = input
-            .iter()
-            .partition(|x| x.chars().nth(index) == Some('0'));
-    }
-
-    let result = counts.0.last().or(counts.1.last()).unwrap();
-    usize::from_str_radix(result, 2).unwrap()
-}
+    // ones in a column among the numbers selected by `mask`
+    fn ones(&self, column: usize, mask: &[u64]) -> usize {
+        self.columns[column].iter().zip(mask).map(|(bits, mask)| (bits & mask).count_ones() as usize).sum()
+    }
 
-fn find_oxygen_generator<'a>(zeros: Vec<&'a str>, ones: Vec<&'a str>) -> Vec<&'a str> {
-    if zeros.len() > ones.len() {
-        zeros
-    } else {
-        ones
+    fn all(&self) -> Vec<u64> {
+        let mut mask = vec![u64::MAX; self.numbers.len().div_ceil(64)];
+        if !self.numbers.len().is_multiple_of(64) {
+            *mask.last_mut().unwrap() = (1 << (self.numbers.len() % 64)) - 1;
+        }
+        mask
+    }
+
+    // most common bit of every column
+    pub fn gamma(&self, tie: TieBreak) -> u64 {
+        let all = self.all();
+        let total = self.numbers.len();
+        (0..self.width).fold(0, |gamma, column| {
+            let ones = self.ones(column, &all);
+            gamma << 1 | Criterion::MostCommon(tie).keep_ones(total - ones, ones) as u64
+        })
+    }
+
+    // Filter numbers column by column, keeping those with the bit picked by
+    // `criterion`, until one is left. None for an empty report.
+    pub fn rating(&self, criterion: Criterion) -> Option<u64> {
+        let mut mask = self.all();
+        let mut remaining = self.numbers.len();
+        for column in 0..self.width {
+            if remaining <= 1 {
+                break;
+            }
+            let ones = self.ones(column, &mask);
+            let zeros = remaining - ones;
+            // a group with no numbers can't be kept
+            let keep_ones = if ones == 0 || zeros == 0 { ones > 0 } else { criterion.keep_ones(zeros, ones) };
+            for (mask, bits) in mask.iter_mut().zip(&self.columns[column]) {
+                *mask &= if keep_ones { *bits } else { !bits };
+            }
+            remaining = if keep_ones { ones } else { zeros };
+        }
+        let first = mask.iter().enumerate().find(|(_, bits)| **bits != 0)?;
+        Some(self.numbers[first.0 * 64 + first.1.trailing_zeros() as usize])
     }
 }
 
-fn find_co2_scrubber<'a>(zeros: Vec<&'a str>, ones: Vec<&'a str>) -> Vec<&'a str> {
-    if zeros.len() <= ones.len() {
-        zeros
-    } else {
-        ones
+pub fn part1(input: &[String]) -> usize {
+    let report = Report::parse(input).expect("invalid diagnostic report");
+    if report.width() == 0 {
+        return 0;
     }
+    let gamma = report.gamma(TieBreak::Zero);
+    let epsilon = !gamma & (u64::MAX >> (64 - report.width()));
+    (gamma * epsilon) as usize
 }
 
 pub fn part2(input: &[String]) -> usize {
-    choosing(input, find_co2_scrubber) * choosing(input, find_oxygen_generator)
+    let report = Report::parse(input).expect("invalid diagnostic report");
+    let oxygen_generator = report.rating(Criterion::MostCommon(TieBreak::One)).unwrap_or(0);
+    let co2_scrubber = report.rating(Criterion::LeastCommon(TieBreak::Zero)).unwrap_or(0);
+    (oxygen_generator * co2_scrubber) as usize
 }
 
+#[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test_part1() {
         let input: Vec<String> = "00100
@@ -117,4 +193,62 @@ mod tests {
         let part2_result = super::part2(&input);
         assert_eq!(part2_result, 230);
     }
+
+    fn example() -> Vec<String> {
+        "00100
+      11110
+      10110
+      10111
+      10101
+      01111
+      00111
+      11100
+      10000
+      11001
+      00010
+      01010"
+            .lines()
+            .map(|x| x.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn ratings() {
+        let report = Report::parse(&example()).unwrap();
+        assert_eq!(report.gamma(TieBreak::Zero), 0b10110);
+        assert_eq!(report.rating(Criterion::MostCommon(TieBreak::One)), Some(23));
+        assert_eq!(report.rating(Criterion::LeastCommon(TieBreak::Zero)), Some(10));
+        // the last oxygen column splits 10110 / 10111 evenly
+        assert_eq!(report.rating(Criterion::MostCommon(TieBreak::Zero)), Some(22));
+
+        let tied: Vec<String> = ["10", "01"].iter().map(|s| s.to_string()).collect();
+        let report = Report::parse(&tied).unwrap();
+        assert_eq!(report.rating(Criterion::MostCommon(TieBreak::One)), Some(0b10));
+        assert_eq!(report.rating(Criterion::MostCommon(TieBreak::Zero)), Some(0b01));
+    }
+
+    #[test]
+    fn wide_reports() {
+        let input: Vec<String> = (0..100u64).map(|i| format!("{:064b}", i << 50 | i)).collect();
+        let report = Report::parse(&input).unwrap();
+        assert_eq!(report.width(), 64);
+        assert_eq!(report.numbers()[99], 99 << 50 | 99);
+        // only the two lowest bits of 0..100 split evenly
+        assert_eq!(report.gamma(TieBreak::One), 0b11 << 50 | 0b11);
+        assert_eq!(report.gamma(TieBreak::Zero), 0);
+    }
+
+    #[test]
+    fn report_errors() {
+        let mut input = example();
+        input[3] = "1011".to_string();
+        assert!(matches!(Report::parse(&input), Err(ReportError::RaggedLine { line: 3, expected: 5, found: 4 })));
+        input[3] = "10x11".to_string();
+        assert!(matches!(Report::parse(&input), Err(ReportError::InvalidBit { line: 3, column: 2, found: 'x' })));
+        assert!(matches!(Report::parse_with_width(&example(), 65), Err(ReportError::TooWide(65))));
+        let mut input = example();
+        input[0] = "001é0".to_string();
+        assert!(matches!(Report::parse(&input), Err(ReportError::InvalidBit { line: 0, column: 3, found: 'é' })));
+        assert!(matches!(Report::parse_with_width(&example(), 4), Err(ReportError::RaggedLine { line: 0, .. })));
+    }
 }
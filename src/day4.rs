@@ -1,119 +1,288 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
 pub type Board = Vec<Vec<u32>>;
 
-fn search(number: u32, board: &Board) -> Option<(usize, usize)> {
-    // assuming this number appear only once
-    for row in 0..board.len() {
-        for col in 0..board[0].len() {
-            if board[row][col] == number {
-                return Some((row, col));
-            }
-        }
-    }
-    None
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Win {
+    pub board: usize,
+    pub number: u32,
+    // sum of the unmarked numbers times the winning number
+    pub score: u32,
 }
 
-fn remove(number: u32, v: &mut Vec<u32>) -> Option<u32> {
-    v.iter().position(|x| *x == number).map(|idx| v.swap_remove(idx))
+#[derive(Debug, PartialEq, Eq)]
+pub struct RaggedBoard {
+    pub board: usize,
 }
 
-pub fn part1(numbers: &[u32], boards: &[Board]) -> u32 {
+// Boards of any (rectangular) size; a line is a full row or column, and
+// optionally one of the two diagonals of a square board.
+pub struct Bingo {
+    boards: Vec<Board>,
+    diagonals: bool,
+    // number -> (board, row, col), in board order
+    index: HashMap<u32, Vec<(usize, usize, usize)>>,
+}
 
-    // Store the remaining number in row/number for fast query
-    // board_idx -> rows -> number
-    let mut rows: Vec<Vec<Vec<u32>>> = Vec::new();
-    let mut cols: Vec<Vec<Vec<u32>>> = Vec::new();
-
-    for board in boards {
-        // Create place for this board
-        let mut board_rows: Vec<Vec<u32>> = vec![Vec::new(); 5];
-        let mut board_cols: Vec<Vec<u32>> = vec![Vec::new(); 5];
-        for row in 0..board.len() {
-            for col in 0..board[0].len() {
-                board_rows[row].push(board[row][col]);
-                board_cols[col].push(board[row][col]);
-            }
+impl Bingo {
+    pub fn new(boards: Vec<Board>, diagonals: bool) -> Result<Self, RaggedBoard> {
+        // the line counters assume every row is as wide as the first
+        if let Some(board) = boards.iter().position(|board| board.iter().any(|row| row.len() != board[0].len())) {
+            return Err(RaggedBoard { board });
         }
-        rows.push(board_rows);
-        cols.push(board_cols);
-    }
-
-
-    for number in numbers {
+        let mut index: HashMap<u32, Vec<(usize, usize, usize)>> = HashMap::new();
         for (board_idx, board) in boards.iter().enumerate() {
-            println!("number {}" , number);
-            if let Some((row, col)) = search(*number, board) {
-                println!("Removing {} - {}", row, col);
-                remove(*number, &mut rows[board_idx][row]);
-                remove(*number, &mut cols[board_idx][col]);
-                if rows[board_idx][row].is_empty() || cols[board_idx][col].is_empty() {
-                    // sum all unmmarked numbers
-                    let unmarked_numbers_sum: u32 = rows[board_idx]
-                    .iter()
-                    .map(|row| row.iter().sum::<u32>())
-                    .sum();
-                    println!("board: {}, number: {}, unmarked number sums: {}", board_idx, number, unmarked_numbers_sum);
-                    return unmarked_numbers_sum  * number;
-                } else {
-                    println!("board: {}, row length {}, column length {}", board_idx, rows[board_idx].len(), cols[board_idx].len());
+            for (row, numbers) in board.iter().enumerate() {
+                for (col, number) in numbers.iter().enumerate() {
+                    index.entry(*number).or_default().push((board_idx, row, col));
                 }
             }
+        }
+        Ok(Bingo { boards, diagonals, index })
+    }
+
+    fn has_diagonals(&self, board: &Board) -> bool {
+        self.diagonals && board.iter().all(|row| row.len() == board.len())
+    }
 
+    // Every board's win, in the order they happen while drawing `numbers`.
+    pub fn play<'a>(&'a self, numbers: &'a [u32]) -> Wins<'a> {
+        let width = |board: &Board| board.first().map_or(0, |row| row.len());
+        Wins {
+            bingo: self,
+            numbers: numbers.iter(),
+            drawn: HashSet::new(),
+            rows: self.boards.iter().map(|board| vec![width(board); board.len()]).collect(),
+            cols: self.boards.iter().map(|board| vec![board.len(); width(board)]).collect(),
+            diagonals: self.boards.iter().map(|board| [board.len(); 2]).collect(),
+            unmarked: self.boards.iter().map(|board| board.iter().flatten().sum()).collect(),
+            won: vec![false; self.boards.len()],
+            pending: VecDeque::new(),
         }
     }
+}
 
-    0
+pub struct Wins<'a> {
+    bingo: &'a Bingo,
+    numbers: std::slice::Iter<'a, u32>,
+    drawn: HashSet<u32>,
+    // unmarked cells left per row, column and diagonal of each board
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>,
+    diagonals: Vec<[usize; 2]>,
+    unmarked: Vec<u32>,
+    won: Vec<bool>,
+    // boards that won on the same number, lowest index first
+    pending: VecDeque<Win>,
 }
 
-//TODO: refactor
-pub fn part2(numbers: &[u32], boards: &[Board]) -> u32 {
-    // Store the remaining number in row/number for fast query
-    // board_idx -> rows -> number
-    let mut rows: Vec<Vec<Vec<u32>>> = Vec::new();
-    let mut cols: Vec<Vec<Vec<u32>>> = Vec::new();
-
-    for board in boards {
-        // Create place for this board
-        let mut board_rows: Vec<Vec<u32>> = vec![Vec::new(); 5];
-        let mut board_cols: Vec<Vec<u32>> = vec![Vec::new(); 5];
-        for row in 0..board.len() {
-            for col in 0..board[0].len() {
-                board_rows[row].push(board[row][col]);
-                board_cols[col].push(board[row][col]);
+impl Wins<'_> {
+    fn draw(&mut self, number: u32) {
+        // (board, completed a line), marking every copy of the number before scoring
+        let mut touched: Vec<(usize, bool)> = Vec::new();
+        for &(board, row, col) in self.bingo.index.get(&number).into_iter().flatten() {
+            if self.won[board] {
+                continue;
+            }
+            self.unmarked[board] -= number;
+            self.rows[board][row] -= 1;
+            self.cols[board][col] -= 1;
+            let mut line = self.rows[board][row] == 0 || self.cols[board][col] == 0;
+
+            let size = self.bingo.boards[board].len();
+            if self.bingo.has_diagonals(&self.bingo.boards[board]) {
+                if row == col {
+                    self.diagonals[board][0] -= 1;
+                    line |= self.diagonals[board][0] == 0;
+                }
+                if row + col == size - 1 {
+                    self.diagonals[board][1] -= 1;
+                    line |= self.diagonals[board][1] == 0;
+                }
+            }
+
+            match touched.last_mut() {
+                Some((last, completed)) if *last == board => *completed |= line,
+                _ => touched.push((board, line)),
+            }
+        }
+
+        for (board, completed) in touched {
+            if completed {
+                self.won[board] = true;
+                self.pending.push_back(Win { board, number, score: self.unmarked[board] * number });
             }
         }
-        rows.push(board_rows);
-        cols.push(board_cols);
     }
+}
 
-    let mut winners: Vec<usize> = Vec::new();
-    let mut last_number: u32 = 1;
+impl Iterator for Wins<'_> {
+    type Item = Win;
 
-    for number in numbers {
-        last_number = *number;
-        for (board_idx, board) in boards.iter().enumerate() {
-            if winners.contains(&board_idx) {
-                continue
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(win) = self.pending.pop_front() {
+                return Some(win);
             }
-            if let Some((row, col)) = search(*number, board) {
-                println!("Removing number {} from board {} at {} - {}", *number, board_idx, row, col);
-                remove(*number, &mut rows[board_idx][row]);
-                remove(*number, &mut cols[board_idx][col]);
-                if rows[board_idx][row].is_empty() || cols[board_idx][col].is_empty() {
-                    println!("Board {} won", board_idx);
-                    winners.push(board_idx)
-                }
+            let number = *self.numbers.next()?;
+            if self.drawn.insert(number) {
+                self.draw(number);
             }
         }
-        if winners.len() == boards.len() {
-            break;
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Game {
+    pub numbers: Vec<u32>,
+    pub boards: Vec<Board>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    // 0-based line of the input
+    InvalidNumber { line: usize, token: String },
+    RaggedBoard { line: usize },
+}
+
+fn parse_number(token: &str, line: usize) -> Result<u32, ParseError> {
+    token.parse().map_err(|_| ParseError::InvalidNumber { line, token: token.to_string() })
+}
+
+// Boards are separated by blank lines. Where those are missing, a block of rows
+// taller than it is wide is split into square boards.
+pub fn parse(input: &str) -> Result<Game, ParseError> {
+    let mut lines = input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let numbers = match lines.next() {
+        Some((idx, line)) => line
+            .trim()
+            .split(',')
+            .map(|token| parse_number(token.trim(), idx))
+            .collect::<Result<Vec<u32>, _>>()?,
+        None => return Err(ParseError::Empty),
+    };
+
+    let mut blocks: Vec<Vec<(usize, Vec<u32>)>> = Vec::new();
+    let mut previous_line = None;
+    for (idx, line) in lines {
+        let row = line.split_whitespace().map(|token| parse_number(token, idx)).collect::<Result<Vec<u32>, _>>()?;
+        if previous_line.is_none_or(|previous| previous + 1 != idx) {
+            blocks.push(Vec::new());
         }
+        blocks.last_mut().unwrap().push((idx, row));
+        previous_line = Some(idx);
+    }
+
+    let mut boards = Vec::new();
+    for block in blocks {
+        let width = block[0].1.len();
+        if let Some((idx, _)) = block.iter().find(|(_, row)| row.len() != width) {
+            return Err(ParseError::RaggedBoard { line: *idx });
+        }
+        let height = if block.len() > width && block.len() % width == 0 { width } else { block.len() };
+        for rows in block.chunks(height) {
+            boards.push(rows.iter().map(|(_, row)| row.clone()).collect());
+        }
+    }
+    Ok(Game { numbers, boards })
+}
+
+pub fn part1(numbers: &[u32], boards: &[Board]) -> u32 {
+    let bingo = Bingo::new(boards.to_vec(), false).unwrap();
+    let first = bingo.play(numbers).next();
+    first.map_or(0, |win| win.score)
+}
+
+pub fn part2(numbers: &[u32], boards: &[Board]) -> u32 {
+    let bingo = Bingo::new(boards.to_vec(), false).unwrap();
+    let last = bingo.play(numbers).last();
+    last.map_or(0, |win| win.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bingo, Board, Game, ParseError, RaggedBoard, Win};
+
+    fn example() -> (Vec<u32>, Vec<Board>) {
+        let game = super::parse(include_str!("input/day4_example.txt")).unwrap();
+        (game.numbers, game.boards)
     }
 
-    let last_winner = winners.last().unwrap();
-    let unmarked_sum: u32 = rows[*last_winner]
-        .iter()
-        .map(|row| row.iter().sum::<u32>())
-        .sum();
-    println!("Last winner: {}, unmarked sum: {}, last number: {}", last_winner, unmarked_sum, last_winner);
-    unmarked_sum * last_number
+    #[test]
+    fn example1() {
+        let (numbers, boards) = example();
+        assert_eq!(super::part1(&numbers, &boards), 4512);
+    }
+
+    #[test]
+    fn example2() {
+        let (numbers, boards) = example();
+        assert_eq!(super::part2(&numbers, &boards), 1924);
+    }
+
+    #[test]
+    fn win_events() {
+        let (numbers, boards) = example();
+        let bingo = Bingo::new(boards, false).unwrap();
+        let wins: Vec<Win> = bingo.play(&numbers).collect();
+        assert_eq!(wins.iter().map(|win| win.board).collect::<Vec<_>>(), [2, 0, 1]);
+        assert_eq!(wins[0], Win { board: 2, number: 24, score: 4512 });
+        assert_eq!(wins[2], Win { board: 1, number: 13, score: 1924 });
+    }
+
+    #[test]
+    fn diagonals_and_rectangular_boards() {
+        let square: Board = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let wide: Board = vec![vec![1, 10, 11, 12], vec![5, 13, 14, 15]];
+        let numbers = [3, 5, 7, 1];
+
+        let wins: Vec<Win> = Bingo::new(vec![square.clone(), wide.clone()], true).unwrap().play(&numbers).collect();
+        assert_eq!(wins, [Win { board: 0, number: 7, score: 30 * 7 }, Win { board: 1, number: 1, score: 75 }]);
+
+        // without diagonals the square board never wins, and the 2x4 board's
+        // first column is complete once 1 is drawn
+        let wins: Vec<Win> = Bingo::new(vec![square, wide], false).unwrap().play(&numbers).collect();
+        assert_eq!(wins, [Win { board: 1, number: 1, score: 75 }]);
+    }
+
+    #[test]
+    fn ragged_boards() {
+        let square: Board = vec![vec![1, 2], vec![3, 4]];
+        assert!(matches!(Bingo::new(vec![square.clone(), vec![vec![1], vec![2, 3]]], false), Err(RaggedBoard { board: 1 })));
+        assert!(matches!(Bingo::new(vec![vec![vec![1, 2], vec![3]], square], false), Err(RaggedBoard { board: 0 })));
+    }
+
+    #[test]
+    fn parse_example() {
+        let (numbers, boards) = example();
+        assert_eq!(numbers.len(), 27);
+        assert_eq!(boards.len(), 3);
+        assert_eq!(boards[0][0], [22, 13, 17, 11, 0]);
+        assert_eq!(boards[2][4], [2, 0, 12, 3, 7]);
+    }
+
+    #[test]
+    fn parse_tolerates_spacing() {
+        let expected = Game {
+            numbers: vec![7, 4, 9],
+            boards: vec![vec![vec![1, 2], vec![3, 4]], vec![vec![5, 6], vec![7, 8]], vec![vec![9, 4, 7]]],
+        };
+        // no blank lines at all, extra spaces and trailing whitespace
+        assert_eq!(super::parse("7,4,9  \n 1  2\n3 4 \n5   6\n 7 8\n\n\n9 4 7\t\n"), Ok(expected));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(super::parse(" \n"), Err(ParseError::Empty));
+        assert_eq!(
+            super::parse("7,x,9\n\n1 2\n3 4"),
+            Err(ParseError::InvalidNumber { line: 0, token: "x".to_string() })
+        );
+        assert_eq!(
+            super::parse("7,4,9\n\n1 2\n3 -4"),
+            Err(ParseError::InvalidNumber { line: 3, token: "-4".to_string() })
+        );
+        assert_eq!(super::parse("7,4,9\n\n1 2\n3 4 5"), Err(ParseError::RaggedBoard { line: 3 }));
+    }
 }
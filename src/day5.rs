@@ -1,60 +1,67 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use itertools::Itertools;
 
 pub type Board = Vec<Vec<i32>>;
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct Point {
-    x: i32,
-    y: i32
+pub struct Point {
+    pub x: i32,
+    pub y: i32
 }
 
 impl Point {
-    fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32) -> Self {
         Point { x, y }
     }
 }
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct Line {
-    start: Point,
-    end: Point
+pub struct Line {
+    pub start: Point,
+    pub end: Point
 }
 
 impl Line {
-    fn is_diagonal(&self) -> bool {
+    pub fn is_diagonal(&self) -> bool {
         (self.start.x != self.end.x) && (self.start.y != self.end.y)
     }
 
-    fn points(&self) -> LineIter {
-        let dx = if self.end.x == self.start.x {
-            0
-        } else if self.end.x > self.start.x {
-            1
-        } else {
-            -1
-        };
+    // spans in i64, as they don't fit in i32 for far apart endpoints
+    fn span(&self) -> (i64, i64) {
+        (self.end.x as i64 - self.start.x as i64, self.end.y as i64 - self.start.y as i64)
+    }
 
-        let dy = if self.end.y == self.start.y {
-            0
-        } else if self.end.y > self.start.y {
-            1
-        } else {
-            -1
-        };
+    // horizontal, vertical or at 45 degrees
+    pub fn is_octilinear(&self) -> bool {
+        let (dx, dy) = self.span();
+        dx == 0 || dy == 0 || dx.abs() == dy.abs()
+    }
 
+    // Bresenham's algorithm, which steps exactly along octilinear lines.
+    pub fn points(&self) -> LineIter {
+        let (dx, dy) = self.span();
+        let step = Point::new(dx.signum() as i32, dy.signum() as i32);
+        let (dx, dy) = (dx.abs(), -dy.abs());
         LineIter {
             current: self.start,
             end: self.end,
-            d: Point::new(dx, dy),
+            step,
+            dx,
+            dy,
+            error: dx + dy,
             done: false,
         }
     }
 }
 
-struct LineIter {
+pub struct LineIter {
     current: Point,
     end: Point,
-    d: Point,
+    step: Point,
+    dx: i64,
+    dy: i64,
+    error: i64,
     done: bool,
 }
 
@@ -63,67 +70,517 @@ impl Iterator for LineIter {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.done {
-            None
+            return None;
+        }
+        let point = self.current;
+        if point == self.end {
+            self.done = true;
         } else {
-            let point = self.current;
-            self.current.x += self.d.x;
-            self.current.y += self.d.y;
-            if point == self.end {
-                self.done = true;
+            let error = 2 * self.error;
+            if error >= self.dy {
+                self.error += self.dy;
+                self.current.x += self.step.x;
+            }
+            if error <= self.dx {
+                self.error += self.dx;
+                self.current.y += self.step.y;
             }
-            Some(point)
         }
+        Some(point)
     }
 }
 
-fn parse_point(s: &str) -> Point {
-    let tokens: Vec<String> = s.split(',').map(String::from).collect();
-    let x: i32 = tokens[0].parse().unwrap();
-    let y: i32 = tokens[1].parse().unwrap();
-    Point { x, y }
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Slopes {
+    // only horizontal, vertical and 45 degree lines, as in the puzzle
+    Strict,
+    // any line, rasterised with Bresenham's algorithm
+    Any,
 }
 
-fn parse_line(s: &str) -> Line {
-    let tokens: Vec<String> = s.split(" -> ").map(String::from).collect();
-    let start = parse_point(&tokens[0]);
-    let end: Point = parse_point(&tokens[1]);
-    Line { start, end }
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // 0-based line of the input
+    MissingArrow { line: usize },
+    InvalidPoint { line: usize, token: String },
+    UnsupportedSlope { line: usize, start: Point, end: Point },
 }
 
-pub fn part1(lines: &[String]) -> i32 {
-    let mut map: HashMap<Point, i32> = HashMap::new();
-    let mut result = 0;
+fn parse_point(s: &str, line: usize) -> Result<Point, ParseError> {
+    let invalid = || ParseError::InvalidPoint { line, token: s.to_string() };
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    let x: i32 = x.trim().parse().map_err(|_| invalid())?;
+    let y: i32 = y.trim().parse().map_err(|_| invalid())?;
+    Ok(Point { x, y })
+}
+
+fn parse_line(s: &str, line: usize) -> Result<Line, ParseError> {
+    let (start, end) = s.split_once("->").ok_or(ParseError::MissingArrow { line })?;
+    let start = parse_point(start.trim(), line)?;
+    let end = parse_point(end.trim(), line)?;
+    Ok(Line { start, end })
+}
+
+// Blank lines are skipped. In strict mode any line that isn't horizontal,
+// vertical or at 45 degrees is an error.
+pub fn parse(input: &[String], slopes: Slopes) -> Result<Vec<Line>, ParseError> {
+    input
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| !s.trim().is_empty())
+        .map(|(idx, s)| {
+            let line = parse_line(s, idx)?;
+            if slopes == Slopes::Strict && !line.is_octilinear() {
+                return Err(ParseError::UnsupportedSlope { line: idx, start: line.start, end: line.end });
+            }
+            Ok(line)
+        })
+        .collect()
+}
+
+// Lines fall into one of four families, each a set of parallel lines
+// a * x + b * y = key. A line is then an interval of a parameter t along it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Family {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal,
+}
+
+const FAMILIES: [Family; 4] = [Family::Horizontal, Family::Vertical, Family::Diagonal, Family::AntiDiagonal];
+
+impl Family {
+    fn of(line: &Line) -> Family {
+        let (dx, dy) = line.span();
+        if dy == 0 {
+            Family::Horizontal
+        } else if dx == 0 {
+            Family::Vertical
+        } else if dx.signum() == dy.signum() {
+            Family::Diagonal
+        } else {
+            Family::AntiDiagonal
+        }
+    }
 
-    for input in lines {
-        let line = parse_line(input);
-        if !line.is_diagonal() {
+    fn coefficients(&self) -> (i64, i64) {
+        match self {
+            Family::Horizontal => (0, 1),
+            Family::Vertical => (1, 0),
+            Family::Diagonal => (1, -1),
+            Family::AntiDiagonal => (1, 1),
+        }
+    }
+
+    fn key(&self, p: Point) -> i64 {
+        let (a, b) = self.coefficients();
+        a * p.x as i64 + b * p.y as i64
+    }
+
+    fn param(&self, p: Point) -> i64 {
+        match self {
+            Family::Vertical => p.y as i64,
+            _ => p.x as i64,
+        }
+    }
+
+    fn point(&self, key: i64, t: i64) -> Point {
+        let (x, y) = match self {
+            Family::Horizontal => (t, key),
+            Family::Vertical => (key, t),
+            Family::Diagonal => (t, t - key),
+            Family::AntiDiagonal => (t, key - t),
+        };
+        Point::new(x as i32, y as i32)
+    }
+
+    // the single point where line `key` of this family meets line `other_key` of `other`
+    fn intersection(&self, key: i64, other: Family, other_key: i64) -> Option<Point> {
+        let (a1, b1) = self.coefficients();
+        let (a2, b2) = other.coefficients();
+        let det = a1 * b2 - a2 * b1;
+        let (x, y) = (key * b2 - other_key * b1, a1 * other_key - a2 * key);
+        (x % det == 0 && y % det == 0).then(|| Point::new((x / det) as i32, (y / det) as i32))
+    }
+}
+
+// Inclusive parameter ranges covered by at least one and at least two lines,
+// merged and sorted, for one line of a family.
+#[derive(Default)]
+struct Coverage {
+    once: Vec<(i64, i64)>,
+    twice: Vec<(i64, i64)>,
+}
+
+impl Coverage {
+    fn sweep(intervals: &[(i64, i64)]) -> Coverage {
+        let mut events: Vec<(i64, i32)> = intervals.iter().flat_map(|&(lo, hi)| [(lo, 1), (hi + 1, -1)]).collect();
+        events.sort_unstable();
+        let mut coverage = Coverage::default();
+        let mut depth = 0;
+        let (mut once_from, mut twice_from) = (0, 0);
+        for (t, delta) in events {
+            let before = depth;
+            depth += delta;
+            match (before >= 1, depth >= 1) {
+                (false, true) => once_from = t,
+                (true, false) => push_merged(&mut coverage.once, once_from, t - 1),
+                _ => {}
+            }
+            match (before >= 2, depth >= 2) {
+                (false, true) => twice_from = t,
+                (true, false) => push_merged(&mut coverage.twice, twice_from, t - 1),
+                _ => {}
+            }
+        }
+        coverage
+    }
+}
+
+fn push_merged(ranges: &mut Vec<(i64, i64)>, lo: i64, hi: i64) {
+    match ranges.last_mut() {
+        Some(last) if last.1 + 1 >= lo => last.1 = last.1.max(hi),
+        _ => ranges.push((lo, hi)),
+    }
+}
+
+fn in_ranges(ranges: &[(i64, i64)], t: i64) -> bool {
+    let i = ranges.partition_point(|&(_, hi)| hi < t);
+    i < ranges.len() && ranges[i].0 <= t
+}
+
+// Counts points covered by two or more lines without visiting every point of
+// octilinear lines: overlaps within a family are found by sweeping each line's
+// intervals, and crossings between families by looking up the lines of the
+// other family in range.
+fn sweep_line(lines: &[Line]) -> usize {
+    let mut intervals: HashMap<Family, BTreeMap<i64, Vec<(i64, i64)>>> = HashMap::new();
+    let mut add = |family: Family, start: Point, end: Point| {
+        let (t1, t2) = (family.param(start), family.param(end));
+        intervals
+            .entry(family)
+            .or_default()
+            .entry(family.key(start))
+            .or_default()
+            .push((t1.min(t2), t1.max(t2)));
+    };
+    for line in lines {
+        if line.is_octilinear() {
+            add(Family::of(line), line.start, line.end);
+        } else {
+            // other slopes go in point by point, as lines of length one
             for point in line.points() {
-                let intersect_count= map.entry(point).or_insert(0);
-                if *intersect_count == 1 {
-                    result += 1;
+                add(Family::Horizontal, point, point);
+            }
+        }
+    }
+    let coverage: HashMap<Family, BTreeMap<i64, Coverage>> = intervals
+        .into_iter()
+        .map(|(family, lines)| (family, lines.into_iter().map(|(key, ts)| (key, Coverage::sweep(&ts))).collect()))
+        .collect();
+
+    let lookup = |family: Family, p: Point| coverage.get(&family).and_then(|lines| lines.get(&family.key(p)));
+    let covered_twice = |p: Point| {
+        FAMILIES.iter().filter(|&&family| lookup(family, p).is_some_and(|c| in_ranges(&c.twice, family.param(p)))).count()
+    };
+
+    let overlaps: usize = coverage
+        .values()
+        .flat_map(|lines| lines.values())
+        .flat_map(|c| &c.twice)
+        .map(|(lo, hi)| (hi - lo + 1) as usize)
+        .sum();
+
+    let mut crossings = HashSet::new();
+    for (i, &family) in FAMILIES.iter().enumerate() {
+        let Some(lines) = coverage.get(&family) else { continue };
+        for &other in &FAMILIES[i + 1..] {
+            let Some(other_lines) = coverage.get(&other) else { continue };
+            for (&key, c) in lines {
+                for &(lo, hi) in &c.once {
+                    // the other family's key changes monotonically along this line
+                    let (k1, k2) = (other.key(family.point(key, lo)), other.key(family.point(key, hi)));
+                    for (&other_key, other_c) in other_lines.range(k1.min(k2)..=k1.max(k2)) {
+                        let Some(p) = family.intersection(key, other, other_key) else { continue };
+                        if in_ranges(&c.once, family.param(p)) && in_ranges(&other_c.once, other.param(p)) {
+                            crossings.insert(p);
+                        }
+                    }
                 }
-                *intersect_count += 1;
             }
         }
     }
+    // A crossing not yet counted as an overlap adds one; one counted as an overlap
+    // in k families was counted k - 1 times too often.
+    crossings.into_iter().fold(overlaps, |count, p| match covered_twice(p) {
+        0 => count + 1,
+        k => count - (k - 1),
+    })
+}
 
-    result
+// How many lines cover each point.
+#[derive(Default, Debug, Clone)]
+pub struct OverlapMap {
+    counts: HashMap<Point, u32>,
 }
 
-pub fn part2(lines: &[String]) -> i32 {
-    let mut map: HashMap<Point, i32> = HashMap::new();
-    let mut result = 0;
-
-    for input in lines {
-        let line = parse_line(input);
-        for point in line.points() {
-            let intersect_count= map.entry(point).or_insert(0);
-            if *intersect_count == 1 {
-                result += 1;
+impl OverlapMap {
+    fn from_lines(lines: &[Line]) -> OverlapMap {
+        let mut map = OverlapMap::default();
+        for line in lines {
+            for point in line.points() {
+                *map.counts.entry(point).or_insert(0) += 1;
             }
-            *intersect_count += 1;
         }
+        map
+    }
+
+    pub fn get(&self, p: Point) -> u32 {
+        self.counts.get(&p).copied().unwrap_or(0)
+    }
+
+    pub fn counts(&self) -> &HashMap<Point, u32> {
+        &self.counts
+    }
+
+    // number of points covered by at least `threshold` lines
+    pub fn at_least(&self, threshold: u32) -> usize {
+        self.counts.values().filter(|&&count| count >= threshold).count()
+    }
+
+    pub fn max(&self) -> u32 {
+        self.counts.values().copied().max().unwrap_or(0)
+    }
+
+    // top left and bottom right corners of the covered points
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let xs = self.counts.keys().map(|p| p.x);
+        let ys = self.counts.keys().map(|p| p.y);
+        Some((Point::new(xs.clone().min()?, ys.clone().min()?), Point::new(xs.max()?, ys.max()?)))
+    }
+
+    // Counts from the origin, like the puzzle's diagram, or from further up and left
+    // if lines go there, to the bottom right corner of the covered points.
+    fn rows(&self) -> Vec<Vec<u32>> {
+        let Some((min, max)) = self.bounds() else { return Vec::new() };
+        let min = Point::new(min.x.min(0), min.y.min(0));
+        (min.y..=max.y)
+            .map(|y| (min.x..=max.x).map(|x| self.get(Point::new(x, y))).collect())
+            .collect()
+    }
+
+    // The diagram from the puzzle: '.' where no line is, otherwise the count,
+    // with '+' for ten or more.
+    pub fn to_ascii(&self) -> String {
+        self.rows()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&count| match count {
+                        0 => '.',
+                        1..=9 => char::from_digit(count, 10).unwrap(),
+                        _ => '+',
+                    })
+                    .collect::<String>() + "\n"
+            })
+            .collect()
     }
 
-    result
+    // plain PGM, one gray level per line overlapping
+    pub fn to_pgm(&self) -> String {
+        let rows = self.rows();
+        let width = rows.first().map_or(0, Vec::len);
+        let body: String = rows.iter().map(|row| row.iter().join(" ") + "\n").collect();
+        format!("P2\n{} {}\n{}\n{}", width, rows.len(), self.max().max(1), body)
+    }
+}
+
+fn without_diagonals(lines: &[Line], diagonals: bool) -> Vec<Line> {
+    lines.iter().copied().filter(|line| diagonals || !line.is_diagonal()).collect()
+}
+
+pub fn overlap_map(lines: &[Line], diagonals: bool) -> OverlapMap {
+    OverlapMap::from_lines(&without_diagonals(lines, diagonals))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    // visit every point of every line
+    Raster,
+    // count overlaps from the line endpoints alone
+    SweepLine,
+}
+
+// Number of points where two or more lines overlap.
+pub fn count_overlaps(lines: &[Line], diagonals: bool, method: Method) -> usize {
+    let lines = without_diagonals(lines, diagonals);
+    match method {
+        Method::Raster => OverlapMap::from_lines(&lines).at_least(2),
+        Method::SweepLine => sweep_line(&lines),
+    }
+}
+
+pub fn part1(lines: &[String]) -> i32 {
+    let lines = parse(lines, Slopes::Strict).unwrap();
+    count_overlaps(&lines, false, Method::Raster) as i32
+}
+
+pub fn part2(lines: &[String]) -> i32 {
+    let lines = parse(lines, Slopes::Strict).unwrap();
+    count_overlaps(&lines, true, Method::Raster) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_overlaps, overlap_map, parse, Line, Method, ParseError, Point, Slopes};
+
+    fn lines(input: &str) -> Vec<Line> {
+        let input: Vec<String> = input.lines().map(String::from).collect();
+        parse(&input, Slopes::Any).unwrap()
+    }
+
+    fn both_methods(input: &[Line], diagonals: bool) -> usize {
+        let raster = count_overlaps(input, diagonals, Method::Raster);
+        assert_eq!(count_overlaps(input, diagonals, Method::SweepLine), raster);
+        raster
+    }
+
+    #[test]
+    fn example() {
+        let input = lines(include_str!("input/day5_example.txt"));
+        assert_eq!(both_methods(&input, false), 5);
+        assert_eq!(both_methods(&input, true), 12);
+    }
+
+    #[test]
+    fn puzzle_input() {
+        let input = lines(include_str!("input/day5.txt"));
+        both_methods(&input, false);
+        both_methods(&input, true);
+    }
+
+    #[test]
+    fn crossings() {
+        // diagonals crossing between grid points don't count
+        assert_eq!(both_methods(&lines("0,0 -> 3,3\n0,3 -> 3,0"), true), 0);
+        assert_eq!(both_methods(&lines("0,0 -> 4,4\n0,4 -> 4,0"), true), 1);
+        // three families through one point count it once
+        assert_eq!(both_methods(&lines("0,2 -> 4,2\n2,0 -> 2,4\n0,0 -> 4,4\n2,2 -> 2,2"), true), 1);
+        // overlaps in two families crossing each other
+        assert_eq!(both_methods(&lines("0,2 -> 4,2\n1,2 -> 3,2\n2,0 -> 2,4\n2,1 -> 2,3"), false), 5);
+        // touching and overlapping collinear lines
+        assert_eq!(both_methods(&lines("0,0 -> 3,0\n3,0 -> 5,0\n4,0 -> 9,0\n1,0 -> 1,0"), false), 4);
+    }
+
+    #[test]
+    fn far_apart_endpoints() {
+        // far too long to rasterise, and their spans overflow i32
+        let input = lines(
+            "-2000000000,0 -> 2000000000,0\n\
+             0,-2147483648 -> 0,2147483647\n\
+             2147483647,-2147483647 -> -2147483647,2147483647\n\
+             -2147483648,-2147483648 -> 2147483647,2147483647"
+        );
+        assert_eq!(count_overlaps(&input, true, Method::SweepLine), 1);
+        let input = lines("-2147483648,5 -> 2147483647,5\n2147483647,5 -> -2000000000,5");
+        assert_eq!(count_overlaps(&input, false, Method::SweepLine), 4147483648);
+    }
+
+    #[test]
+    fn random_lines() {
+        // a small LCG keeps the test deterministic
+        let mut state: u64 = 2021;
+        let mut next = |n: i32| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as i32 % n
+        };
+        for _ in 0..50 {
+            let input: String = (0..60)
+                .map(|_| {
+                    let (x, y, len) = (next(20), next(20), next(15));
+                    let (dx, dy) = [(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (0, -1), (-1, -1), (-1, 1)][next(8) as usize];
+                    format!("{},{} -> {},{}", x, y, x + dx * len, y + dy * len)
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            both_methods(&lines(&input), true);
+        }
+    }
+
+    #[test]
+    fn heatmap() {
+        let input = lines(include_str!("input/day5_example.txt"));
+        let map = overlap_map(&input, true);
+        assert_eq!(
+            map.to_ascii(),
+            "1.1....11.\n\
+             .111...2..\n\
+             ..2.1.111.\n\
+             ...1.2.2..\n\
+             .112313211\n\
+             ...1.2....\n\
+             ..1...1...\n\
+             .1.....1..\n\
+             1.......1.\n\
+             222111....\n"
+        );
+        assert_eq!(map.get(Point::new(4, 4)), 3);
+        assert_eq!(map.at_least(2), 12);
+        assert_eq!(map.at_least(3), 2);
+        assert_eq!(map.at_least(4), 0);
+        let pgm = map.to_pgm();
+        assert!(pgm.starts_with("P2\n10 10\n3\n1 0 1 0 0 0 0 1 1 0\n"));
+        assert_eq!(pgm.lines().count(), 13);
+
+        // lines away from the axes still get drawn from the origin
+        let map = overlap_map(&lines("2,3 -> 4,3\n3,1 -> 3,4"), true);
+        assert_eq!(map.bounds(), Some((Point::new(2, 1), Point::new(4, 4))));
+        assert_eq!(map.to_ascii(), ".....\n...1.\n...1.\n..121\n...1.\n");
+        assert!(map.to_pgm().starts_with("P2\n5 5\n2\n0 0 0 0 0\n"));
+        let map = overlap_map(&lines("-1,-1 -> 0,0"), true);
+        assert_eq!(map.to_ascii(), "1.\n.1\n");
+    }
+
+    #[test]
+    fn any_slope() {
+        let line = Line { start: Point::new(0, 0), end: Point::new(4, 1) };
+        let points: Vec<Point> = line.points().collect();
+        assert_eq!(points, [(0, 0), (1, 0), (2, 1), (3, 1), (4, 1)].map(|(x, y)| Point::new(x, y)));
+        let steep: Vec<Point> = Line { start: Point::new(1, 3), end: Point::new(0, 0) }.points().collect();
+        assert_eq!(steep, [(1, 3), (1, 2), (0, 1), (0, 0)].map(|(x, y)| Point::new(x, y)));
+
+        let input = lines("0,0 -> 4,1\n2,0 -> 2,5\n0,3 -> 6,0\n4,1 -> 4,1");
+        assert_eq!(overlap_map(&input, true).at_least(2), 4);
+        assert_eq!(both_methods(&input, true), 4);
+    }
+
+    #[test]
+    fn long_lines() {
+        let input = ["-1500000000,-1500000000 -> 1500000000,1500000000".to_string()];
+        assert_eq!(parse(&input, Slopes::Strict).unwrap().len(), 1);
+        let input = ["-2147483648,-2147483648 -> 2147483647,0".to_string()];
+        assert!(parse(&input, Slopes::Strict).is_err());
+        let line = parse(&input, Slopes::Any).unwrap()[0];
+        let points: Vec<Point> = line.points().take(3).collect();
+        // a slope just over 1/2
+        let expected = [(-2147483648, -2147483648), (-2147483647, -2147483647), (-2147483646, -2147483647)];
+        assert_eq!(points, expected.map(|(x, y)| Point::new(x, y)));
+        let point = Line { start: Point::new(i32::MAX, i32::MIN), end: Point::new(i32::MAX, i32::MIN) };
+        assert_eq!(point.points().count(), 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let input: Vec<String> = ["0,0 -> 2,2", "", "0,0 -> 4,1"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            parse(&input, Slopes::Strict),
+            Err(ParseError::UnsupportedSlope { line: 2, start: Point::new(0, 0), end: Point::new(4, 1) })
+        );
+        assert_eq!(parse(&input, Slopes::Any).unwrap().len(), 2);
+        let input = ["0,0 => 2,2".to_string()];
+        assert_eq!(parse(&input, Slopes::Any), Err(ParseError::MissingArrow { line: 0 }));
+        let input = ["0,0 -> 2;2".to_string()];
+        assert_eq!(parse(&input, Slopes::Any), Err(ParseError::InvalidPoint { line: 0, token: "2;2".to_string() }));
+    }
 }
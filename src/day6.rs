@@ -1,19 +1,80 @@
-fn simulation(initial_fish: &[u64], simulation_days: u64) -> u64 {
-    let mut simulation: Vec<u64> = vec![0; 9];
-    for fish in initial_fish {
-        simulation[*fish as usize] += 1;
+use std::num::NonZeroU64;
+
+use crate::matrix::{mul_matrix, mul_vector, Arithmetic, Matrix, Overflow};
+
+// Timer values a fish goes back to after reproducing, and starts a newborn at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Lifecycle {
+    pub reset: usize,
+    pub newborn: usize,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Lifecycle { reset: 6, newborn: 8 }
+    }
+}
+
+impl Lifecycle {
+    fn slots(&self, initial_fish: &[u64]) -> usize {
+        let oldest = initial_fish.iter().max().copied().unwrap_or(0) as usize;
+        self.reset.max(self.newborn).max(oldest) + 1
+    }
+
+    fn initial(&self, initial_fish: &[u64]) -> Vec<u64> {
+        let mut simulation: Vec<u64> = vec![0; self.slots(initial_fish)];
+        for fish in initial_fish {
+            simulation[*fish as usize] += 1;
+        }
+        simulation
     }
 
-    for _ in 0..simulation_days {
-        let reproduce_count = simulation[0];
-        simulation[0] = 0; // clear this bit because we don't want to wrap around
-        simulation.rotate_left(1);
-        // there are some more new fish
-        simulation[8] = reproduce_count;
-        simulation[6] += reproduce_count;
+    // Fish per timer value after `days`, or Overflow once a count no longer fits.
+    pub fn population(&self, initial_fish: &[u64], days: u64) -> Result<Vec<u64>, Overflow> {
+        let mut simulation = self.initial(initial_fish);
+        for _ in 0..days {
+            let reproduce_count = simulation[0];
+            simulation[0] = 0; // clear this bit because we don't want to wrap around
+            simulation.rotate_left(1);
+            // there are some more new fish
+            simulation[self.newborn] = simulation[self.newborn].checked_add(reproduce_count).ok_or(Overflow)?;
+            simulation[self.reset] = simulation[self.reset].checked_add(reproduce_count).ok_or(Overflow)?;
+        }
+        Ok(simulation)
     }
 
-    simulation.iter().sum()
+    // Same as `population`, modulo `modulus`, by raising the one-day transition
+    // matrix to the power `days`; fine for days in the billions.
+    pub fn population_mod(&self, initial_fish: &[u64], mut days: u64, modulus: NonZeroU64) -> Vec<u64> {
+        let arithmetic = Arithmetic::Modular(modulus);
+        let reduce = |count: u64| count % modulus.get();
+        let slots = self.slots(initial_fish);
+        // column `from` holds where the fish with that timer are a day later
+        let mut power: Matrix = vec![vec![0; slots]; slots];
+        for from in 1..slots {
+            power[from - 1][from] = 1;
+        }
+        power[self.reset][0] += 1;
+        power[self.newborn][0] += 1;
+        let mut power: Matrix = power.into_iter().map(|row| row.into_iter().map(reduce).collect()).collect();
+
+        let mut simulation: Vec<u64> = self.initial(initial_fish).into_iter().map(reduce).collect();
+        // modular arithmetic never overflows
+        while days > 0 {
+            if days & 1 == 1 {
+                simulation = mul_vector(&power, &simulation, arithmetic).unwrap();
+            }
+            days >>= 1;
+            if days > 0 {
+                power = mul_matrix(&power, &power, arithmetic).unwrap();
+            }
+        }
+        simulation
+    }
+}
+
+fn simulation(initial_fish: &[u64], simulation_days: u64) -> u64 {
+    Lifecycle::default().population(initial_fish, simulation_days).unwrap().iter().sum()
 }
 
 pub fn part1(initial_fish: &[u64]) -> u64 {
@@ -23,3 +84,53 @@ pub fn part1(initial_fish: &[u64]) -> u64 {
 pub fn part2(initial_fish: &[u64]) -> u64 {
     simulation(initial_fish, 256)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Lifecycle, Overflow};
+
+    const EXAMPLE: [u64; 5] = [3, 4, 3, 1, 2];
+
+    #[test]
+    fn example() {
+        assert_eq!(super::simulation(&EXAMPLE, 18), 26);
+        assert_eq!(super::part1(&EXAMPLE), 5934);
+        assert_eq!(super::part2(&EXAMPLE), 26984457539);
+    }
+
+    #[test]
+    fn population_by_timer() {
+        let lifecycle = Lifecycle::default();
+        assert_eq!(lifecycle.population(&EXAMPLE, 1), Ok(vec![1, 1, 2, 1, 0, 0, 0, 0, 0]));
+        assert_eq!(lifecycle.population(&EXAMPLE, 2), Ok(vec![1, 2, 1, 0, 0, 0, 1, 0, 1]));
+        assert_eq!(lifecycle.population(&EXAMPLE, 1000), Err(Overflow));
+
+        // every fish splits in two each day
+        let doubling = Lifecycle { reset: 0, newborn: 0 };
+        assert_eq!(doubling.population(&[0], 10), Ok(vec![1024]));
+    }
+
+    #[test]
+    fn modular_population() {
+        use std::num::NonZeroU64;
+
+        const P: u64 = 1_000_000_007;
+        let modulus = NonZeroU64::new(P).unwrap();
+        let lifecycle = Lifecycle::default();
+        let exact = lifecycle.population(&EXAMPLE, 256).unwrap();
+        let modular = lifecycle.population_mod(&EXAMPLE, 256, modulus);
+        assert_eq!(modular, exact.iter().map(|count| count % P).collect::<Vec<_>>());
+
+        // 2^(10^12) mod P, by Fermat's little theorem 2^(10^12 mod (P - 1))
+        let doubling = Lifecycle { reset: 0, newborn: 0 };
+        let days: u64 = 1_000_000_000_000;
+        assert_eq!(
+            doubling.population_mod(&[0], days, modulus),
+            doubling.population_mod(&[0], days % (P - 1), modulus)
+        );
+        assert_eq!(lifecycle.population_mod(&EXAMPLE, 1_000_000_000, modulus).len(), 9);
+        // everything is 0 mod 1
+        let one = NonZeroU64::new(1).unwrap();
+        assert_eq!(lifecycle.population_mod(&EXAMPLE, 0, one), vec![0; 9]);
+    }
+}
@@ -1,7 +1,5 @@
 use std::collections::HashMap;
 
-use itertools::*;
-
 pub fn part1(lines: &[String]) -> usize {
     let mut segment_index: HashMap<usize, Vec<u8>> = HashMap::new();
     segment_index.insert(2, vec![1]);
@@ -27,72 +25,150 @@ pub fn part1(lines: &[String]) -> usize {
     ans
 }
 
-#[allow(dead_code)]
-struct Mapping {
-    forward_mapping: Vec<u8>,
-    inverse_mapping: Vec<u8>,
-    alphabets: Vec<u8>,
+// Which segment each wire drives: wire i ('a' + i) lights segment mapping[i].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Wiring {
+    mapping: [u8; 7],
 }
 
-impl Mapping {
-
-    fn from(forward_mapping: &[u8]) -> Self {
-        let orig_segments: Vec<u8> = (0..=9).map(digit_to_segment).collect();
+impl Wiring {
+    pub fn segment(&self, wire: char) -> Option<char> {
+        let wire = (wire as u8).checked_sub(b'a').filter(|&wire| wire < 7)?;
+        Some((b'a' + self.mapping[wire as usize]) as char)
+    }
 
-        let inverse_mapping: Vec<u8> = inverse(forward_mapping);
-        let forward_mapping: Vec<u8> = forward_mapping.to_vec();
-        let alphabets: Vec<u8> = orig_segments.iter().map(|segment| apply_mapping(*segment, &forward_mapping)).collect();
-        
-        Mapping {
-            forward_mapping, inverse_mapping, alphabets
-        }
+    // the digit shown by a pattern of lit wires, if it is one
+    pub fn digit(&self, pattern: &str) -> Option<u8> {
+        segment_to_digit(apply_mapping(bitmask(pattern), &inverse(&self.mapping)))
     }
 }
 
-fn generate_all_mappings() -> Vec<Mapping> {
-    let unit_mapping: Vec<u8> = vec![0,1,2,3,4,5,6];
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    MissingSeparator,
+    // wires outside 'a'..='g', or the same wire twice
+    InvalidPattern(String),
+    // wires that could still drive more than one segment
+    Ambiguous { wires: String },
+    // no wiring lights every pattern as a digit
+    Inconsistent,
+    // an output that isn't a digit under the deduced wiring
+    UnknownDigit(String),
+    // more output digits than fit in a u32
+    TooManyOutputs(usize),
+}
 
-    let mut all_mappings: Vec<Mapping> = Vec::new();
-    for mapping in unit_mapping.iter().permutations(7) {
-        let forward_mapping: Vec<u8> = mapping.iter().map(|&x| *x).collect();
-        all_mappings.push(Mapping::from(&forward_mapping));
+fn check_pattern(pattern: &str) -> Result<(), DecodeError> {
+    let mut seen = 0u8;
+    for wire in pattern.bytes() {
+        if !(b'a'..=b'g').contains(&wire) || seen & (1 << (wire - b'a')) != 0 {
+            return Err(DecodeError::InvalidPattern(pattern.to_string()));
+        }
+        seen |= 1 << (wire - b'a');
     }
-    all_mappings
+    Ok(())
 }
 
-pub fn part2(lines: &[String]) -> u64 {
-    let mut ans: u64 = 0;
-    let all_mappings: Vec<Mapping> = generate_all_mappings();
-    // let mapping: Vec<u8> = vec![2,5,6,0,1,3,4];
-    // let all_mappings: Vec<Mapping> = vec![Mapping::from(&mapping)];
-
-    for line in lines {
-        let tokens: Vec<&str> = line.split('|').collect();
-        let inputs: Vec<&str> = tokens[0].trim().split(' ').collect();
-        let outputs: Vec<&str> = tokens[1].trim().split(' ').collect();
-
-        let mut line_output: u64 = 0;
+// Segments lit by every digit with `count` segments, and by any of them.
+fn segments_with_count(count: u32) -> (u8, u8) {
+    (0..=9)
+        .map(digit_to_segment)
+        .filter(|segments| segments.count_ones() == count)
+        .fold((0b1111111, 0), |(all, any), segments| (all & segments, any | segments))
+}
 
-        'mapping: for mapping in &all_mappings {
-            for &input in &inputs {
-                let segment = bitmask(input);
-                if !mapping.alphabets.contains(&segment) {
-                    continue 'mapping
-                }
+// Deduces the wiring from the patterns seen on a display. Each pattern limits
+// its wires to the segments of the digits with that many segments, and keeps
+// the other wires off the segments those digits all share; wires (or segments)
+// left with a single option are then settled until nothing changes.
+pub fn deduce_wiring(patterns: &[&str]) -> Result<Wiring, DecodeError> {
+    patterns.iter().try_for_each(|pattern| check_pattern(pattern))?;
+    // candidates[wire] is a mask of segments, in the bit order of bitmask()
+    let mut candidates = [0b1111111u8; 7];
+    for pattern in patterns {
+        let (all, any) = segments_with_count(pattern.len() as u32);
+        let wires = bitmask(pattern);
+        for (wire, candidate) in candidates.iter_mut().enumerate() {
+            if wires & (1 << (6 - wire)) != 0 {
+                *candidate &= any;
+            } else {
+                *candidate &= !all;
             }
+        }
+    }
 
-            for &output in &outputs {
-                let segment = bitmask(output);
-                if !mapping.alphabets.contains(&segment) {
-                    continue 'mapping
+    loop {
+        let before = candidates;
+        for wire in 0..7 {
+            if candidates[wire].count_ones() == 1 {
+                for other in (0..7).filter(|&other| other != wire) {
+                    candidates[other] &= !candidates[wire];
                 }
-                let digit = segment_to_digit(apply_mapping(segment, &mapping.inverse_mapping));
-                line_output = line_output * 10 + digit as u64;
             }
-            ans += line_output;
+        }
+        for segment in (0..7).map(|segment| 1u8 << segment) {
+            let wires: Vec<usize> = (0..7).filter(|&wire| candidates[wire] & segment != 0).collect();
+            match wires[..] {
+                [] => return Err(DecodeError::Inconsistent),
+                [wire] => candidates[wire] = segment,
+                _ => {}
+            }
+        }
+        if candidates.contains(&0) {
+            return Err(DecodeError::Inconsistent);
+        }
+        if candidates == before {
+            break;
         }
     }
-    ans
+
+    let wires: String = (0..7)
+        .filter(|&wire| candidates[wire].count_ones() > 1)
+        .map(|wire| (b'a' + wire as u8) as char)
+        .collect();
+    if !wires.is_empty() {
+        return Err(DecodeError::Ambiguous { wires });
+    }
+    let wiring = Wiring { mapping: candidates.map(|segment| 6 - segment.trailing_zeros() as u8) };
+    if patterns.iter().any(|pattern| wiring.digit(pattern).is_none()) {
+        return Err(DecodeError::Inconsistent);
+    }
+    Ok(wiring)
+}
+
+fn split_line(line: &str) -> Result<(Vec<&str>, Vec<&str>), DecodeError> {
+    let (patterns, outputs) = line.split_once('|').ok_or(DecodeError::MissingSeparator)?;
+    Ok((patterns.split_whitespace().collect(), outputs.split_whitespace().collect()))
+}
+
+// The wiring of every display, from the patterns before the '|'.
+pub fn wirings(lines: &[String]) -> Vec<Result<Wiring, DecodeError>> {
+    lines
+        .iter()
+        .map(|line| deduce_wiring(&split_line(line)?.0))
+        .collect()
+}
+
+// The number shown by the outputs after the '|'.
+pub fn decode_line(line: &str) -> Result<u32, DecodeError> {
+    let (patterns, outputs) = split_line(line)?;
+    let wiring = deduce_wiring(&patterns)?;
+    outputs.iter().try_fold(0u32, |value, output| {
+        check_pattern(output)?;
+        let digit = wiring.digit(output).ok_or_else(|| DecodeError::UnknownDigit(output.to_string()))?;
+        value
+            .checked_mul(10)
+            .and_then(|value| value.checked_add(digit as u32))
+            .ok_or(DecodeError::TooManyOutputs(outputs.len()))
+    })
+}
+
+pub fn part2(lines: &[String]) -> u64 {
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| decode_line(line).unwrap() as u64)
+        .sum()
 }
 
 fn bitmask(word: &str) -> u8 {
@@ -152,11 +228,14 @@ fn digit_to_segment(digit: u8) -> u8 {
     }
 }
 
-fn segment_to_digit(segment: u8) -> u8 {
-    (0..=9).find(|digit| digit_to_segment(*digit) == segment).unwrap()
+fn segment_to_digit(segment: u8) -> Option<u8> {
+    (0..=9).find(|digit| digit_to_segment(*digit) == segment)
 }
 
+#[cfg(test)]
 mod tests {
+    use super::{decode_line, deduce_wiring, DecodeError};
+
     #[test]
     fn test_format() {
         assert_eq!(super::format_digit(super::bitmask("cf")), "cf");
@@ -220,4 +299,53 @@ mod tests {
         let inverse_mapping = super::inverse(&mapping);
         assert_eq!(super::apply_mapping(super::bitmask("ab"), &inverse_mapping), super::digit_to_segment(1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn deduce_sample_wiring() {
+        let patterns: Vec<&str> = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab".split(' ').collect();
+        let wiring = deduce_wiring(&patterns).unwrap();
+        assert_eq!(wiring, super::Wiring { mapping: [2, 5, 6, 0, 1, 3, 4] });
+        assert_eq!(wiring.segment('d'), Some('a'));
+        assert_eq!(wiring.segment('x'), None);
+        assert_eq!(wiring.digit("cdfeb"), Some(5));
+        assert_eq!(wiring.digit("ab"), Some(1));
+        assert_eq!(wiring.digit("abc"), None);
+    }
+
+    #[test]
+    fn deduce_errors() {
+        assert_eq!(deduce_wiring(&["ab", "abd", "abef"]), Err(DecodeError::Ambiguous { wires: "abcefg".to_string() }));
+        // two different wirings of a one
+        assert_eq!(deduce_wiring(&["ab", "cd"]), Err(DecodeError::Inconsistent));
+        // a five-wire pattern missing one of the segments every five-segment digit lights
+        let patterns: Vec<&str> = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab abcde".split(' ').collect();
+        assert_eq!(deduce_wiring(&patterns), Err(DecodeError::Inconsistent));
+    }
+
+    #[test]
+    fn decode_example_displays() {
+        let expected = [8394, 9781, 1197, 9361, 4873, 8418, 4548, 1625, 8717, 4315];
+        let lines: Vec<&str> = include_str!("input/day8_example.txt").lines().collect();
+        assert_eq!(lines.len(), expected.len());
+        for (line, expected) in lines.iter().zip(expected) {
+            assert_eq!(decode_line(line), Ok(expected), "{}", line);
+        }
+        let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(super::part2(&lines), 61229);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let patterns = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab";
+        assert_eq!(decode_line(&format!("{} | cdfeb fcadb cdfeb cdbaf", patterns)), Ok(5353));
+        assert_eq!(decode_line(patterns), Err(DecodeError::MissingSeparator));
+        assert_eq!(decode_line(&format!("{} | cdfeb abc", patterns)), Err(DecodeError::UnknownDigit("abc".to_string())));
+        assert_eq!(decode_line(&format!("{} | cdfeb abx", patterns)), Err(DecodeError::InvalidPattern("abx".to_string())));
+        assert_eq!(decode_line("ab aab | ab"), Err(DecodeError::InvalidPattern("aab".to_string())));
+        assert_eq!(decode_line("ab dab | ab"), Err(DecodeError::Ambiguous { wires: "abcefg".to_string() }));
+        // 4294967295 is the largest u32
+        assert_eq!(decode_line(&format!("{} | {}", patterns, ["eafb"; 9].join(" "))), Ok(444444444));
+        assert_eq!(decode_line(&format!("{} | {}", patterns, ["eafb"; 10].join(" "))), Err(DecodeError::TooManyOutputs(10)));
+        assert_eq!(decode_line(&format!("{} | {}", patterns, ["ab"; 10].join(" "))), Ok(1111111111));
+    }
+}
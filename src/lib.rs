@@ -29,3 +29,5 @@ pub mod day17;
 pub mod day18;
 pub mod day19;
 pub mod day20;
+
+pub mod matrix;
@@ -0,0 +1,51 @@
+// Matrix powers over u64 counts, either checked for overflow or modulo some
+// number, for the days that step linear recurrences a huge number of times.
+use std::num::NonZeroU64;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Arithmetic {
+    // fail rather than wrap; note that intermediate matrix powers can overflow
+    // before the final counts would
+    Checked,
+    Modular(NonZeroU64),
+}
+
+impl Arithmetic {
+    pub fn add(self, a: u64, b: u64) -> Result<u64, Overflow> {
+        match self {
+            Arithmetic::Checked => a.checked_add(b).ok_or(Overflow),
+            Arithmetic::Modular(m) => Ok(((a as u128 + b as u128) % m.get() as u128) as u64),
+        }
+    }
+
+    pub fn mul(self, a: u64, b: u64) -> Result<u64, Overflow> {
+        match self {
+            Arithmetic::Checked => a.checked_mul(b).ok_or(Overflow),
+            Arithmetic::Modular(m) => Ok((a as u128 * b as u128 % m.get() as u128) as u64),
+        }
+    }
+}
+
+pub type Matrix = Vec<Vec<u64>>;
+
+pub fn mul_matrix(a: &Matrix, b: &Matrix, arithmetic: Arithmetic) -> Result<Matrix, Overflow> {
+    let n = a.len();
+    let mut product = vec![vec![0; n]; n];
+    for (i, row) in a.iter().enumerate() {
+        for (k, &a_ik) in row.iter().enumerate().filter(|(_, &a_ik)| a_ik != 0) {
+            for j in 0..n {
+                product[i][j] = arithmetic.add(product[i][j], arithmetic.mul(a_ik, b[k][j])?)?;
+            }
+        }
+    }
+    Ok(product)
+}
+
+pub fn mul_vector(a: &Matrix, v: &[u64], arithmetic: Arithmetic) -> Result<Vec<u64>, Overflow> {
+    a.iter()
+        .map(|row| row.iter().zip(v).try_fold(0, |sum, (&x, &y)| arithmetic.add(sum, arithmetic.mul(x, y)?)))
+        .collect()
+}